@@ -0,0 +1,250 @@
+use std::str::FromStr;
+
+use crate::error::Error;
+use bdk::bitcoin::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::Network;
+use bdk::descriptor::{Descriptor, DescriptorPublicKey};
+
+use serde_json::json;
+
+/// Single-signature script types with their standard derivation schemes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptType {
+    Legacy,
+    NestedSegwit,
+    Segwit,
+    Taproot,
+}
+
+impl ScriptType {
+    pub const ALL: [ScriptType; 4] = [
+        ScriptType::Legacy,
+        ScriptType::NestedSegwit,
+        ScriptType::Segwit,
+        ScriptType::Taproot,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptType::Legacy => "legacy",
+            ScriptType::NestedSegwit => "nested-segwit",
+            ScriptType::Segwit => "segwit",
+            ScriptType::Taproot => "taproot",
+        }
+    }
+
+    /// BIP44, BIP49, BIP84 and BIP86
+    pub fn purpose(&self) -> u32 {
+        match self {
+            ScriptType::Legacy => 44,
+            ScriptType::NestedSegwit => 49,
+            ScriptType::Segwit => 84,
+            ScriptType::Taproot => 86,
+        }
+    }
+
+    /// Wraps a key expression into the output descriptor of the script type
+    pub fn descriptor(&self, key: &str) -> String {
+        match self {
+            ScriptType::Legacy => format!("pkh({})", key),
+            ScriptType::NestedSegwit => format!("sh(wpkh({}))", key),
+            ScriptType::Segwit => format!("wpkh({})", key),
+            ScriptType::Taproot => format!("tr({})", key),
+        }
+    }
+
+    /// SLIP-132 version of the extended public keys, taproot has none registered
+    pub fn slip132(&self, network: Network) -> &'static str {
+        match (self, network) {
+            (ScriptType::NestedSegwit, Network::Bitcoin) => "ypub",
+            (ScriptType::NestedSegwit, _) => "upub",
+            (ScriptType::Segwit, Network::Bitcoin) => "zpub",
+            (ScriptType::Segwit, _) => "vpub",
+            (_, Network::Bitcoin) => "xpub",
+            (_, _) => "tpub",
+        }
+    }
+
+    /// Account path `m/purpose'/coin_type'/account'`, coin type 1 on the test networks
+    pub fn account_path(&self, network: Network, account: u32) -> Result<DerivationPath, Error> {
+        let coin_type = match network {
+            Network::Bitcoin => 0,
+            _ => 1,
+        };
+
+        Ok(vec![
+            ChildNumber::from_hardened_idx(self.purpose())?,
+            ChildNumber::from_hardened_idx(coin_type)?,
+            ChildNumber::from_hardened_idx(account)?,
+        ]
+        .into())
+    }
+}
+
+impl FromStr for ScriptType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ScriptType::ALL
+            .into_iter()
+            .find(|script_type| script_type.name() == s)
+            .ok_or_else(|| format!("unknown script type {}", s))
+    }
+}
+
+/// Unspendable BIP341 internal key, the taproot multisig outputs are spent through their leaf only
+pub const NUMS_KEY: &str = "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// Multisig script types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultisigType {
+    Wsh,
+    ShWsh,
+    Tr,
+}
+
+impl MultisigType {
+    pub const ALL: [MultisigType; 3] = [MultisigType::Wsh, MultisigType::ShWsh, MultisigType::Tr];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MultisigType::Wsh => "wsh",
+            MultisigType::ShWsh => "sh-wsh",
+            MultisigType::Tr => "tr",
+        }
+    }
+
+    /// Output descriptor of a `threshold` of the keys, sorted except in the taproot multi_a leaf
+    pub fn descriptor(&self, threshold: usize, keys: &[String]) -> String {
+        let keys = keys.join(",");
+        match self {
+            MultisigType::Wsh => format!("wsh(sortedmulti({},{}))", threshold, keys),
+            MultisigType::ShWsh => format!("sh(wsh(sortedmulti({},{})))", threshold, keys),
+            MultisigType::Tr => format!("tr({},multi_a({},{}))", NUMS_KEY, threshold, keys),
+        }
+    }
+
+    /// BIP48 account path `m/48'/coin_type'/account'/script_type'`, BIP87 `m/87'/coin_type'/account'` for taproot
+    pub fn account_path(&self, network: Network, account: u32) -> Result<DerivationPath, Error> {
+        let coin_type = match network {
+            Network::Bitcoin => 0,
+            _ => 1,
+        };
+        let (purpose, script_type) = match self {
+            MultisigType::Wsh => (48, Some(2)),
+            MultisigType::ShWsh => (48, Some(1)),
+            MultisigType::Tr => (87, None),
+        };
+
+        let mut path = vec![
+            ChildNumber::from_hardened_idx(purpose)?,
+            ChildNumber::from_hardened_idx(coin_type)?,
+            ChildNumber::from_hardened_idx(account)?,
+        ];
+        if let Some(script_type) = script_type {
+            path.push(ChildNumber::from_hardened_idx(script_type)?);
+        }
+
+        Ok(path.into())
+    }
+
+    /// `Format` of the Coldcard multisig file, taproot is not supported by it
+    pub fn coldcard(&self) -> Option<&'static str> {
+        match self {
+            MultisigType::Wsh => Some("P2WSH"),
+            MultisigType::ShWsh => Some("P2SH-P2WSH"),
+            MultisigType::Tr => None,
+        }
+    }
+}
+
+impl FromStr for MultisigType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MultisigType::ALL
+            .into_iter()
+            .find(|multisig_type| multisig_type.name() == s)
+            .ok_or_else(|| format!("unknown multisig script type {}", s))
+    }
+}
+
+/// Key expression with its origin, e.g. `[73c5da0a/84'/0'/0']xpub...`
+pub fn origin_key(fingerprint: &str, path: &DerivationPath, key: &str) -> String {
+    format!(
+        "[{}{}]{}",
+        fingerprint,
+        path.to_string().trim_start_matches('m'),
+        key
+    )
+}
+
+/// Parses a public descriptor and returns it with its checksum
+pub fn checksummed(descriptor: &str) -> Result<String, Error> {
+    let descriptor = Descriptor::<DescriptorPublicKey>::from_str(descriptor)?;
+
+    Ok(descriptor.to_string())
+}
+
+/// Account xpub and receive/change descriptors of a master key, for every script type
+pub fn accounts(xprv: &ExtendedPrivKey, account: u32) -> Result<serde_json::Value, Error> {
+    let secp = Secp256k1::new();
+    let fingerprint = xprv.fingerprint(&secp).to_string();
+
+    let mut accounts = serde_json::Map::new();
+    for script_type in ScriptType::ALL {
+        let path = script_type.account_path(xprv.network, account)?;
+        let xpub = ExtendedPubKey::from_priv(&secp, &xprv.derive_priv(&secp, &path)?);
+        let key = origin_key(&fingerprint, &path, &xpub.to_string());
+
+        accounts.insert(
+            format!("bip{}", script_type.purpose()),
+            json!({
+                "path": path.to_string(),
+                "xpub": key,
+                "receive": checksummed(&script_type.descriptor(&format!("{}/0/*", key)))?,
+                "change": checksummed(&script_type.descriptor(&format!("{}/1/*", key)))?,
+            }),
+        );
+    }
+
+    Ok(serde_json::Value::Object(accounts))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_accounts() {
+        // mnemonic: abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about
+        let xprv = ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap();
+        let accounts = accounts(&xprv, 0).unwrap();
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki#test-vectors
+        assert_eq!(accounts["bip84"]["path"], "m/84'/0'/0'");
+        assert_eq!(accounts["bip84"]["xpub"], "[73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V");
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+        let receive = accounts["bip86"]["receive"].as_str().unwrap();
+        assert!(receive.starts_with("tr([73c5da0a/86'/0'/0']xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ/0/*)#"));
+
+        let change = accounts["bip49"]["change"].as_str().unwrap();
+        assert!(change.starts_with("sh(wpkh([73c5da0a/49'/0'/0']"));
+        assert!(change.contains("/1/*))#"));
+        assert!(accounts["bip44"]["receive"]
+            .as_str()
+            .unwrap()
+            .starts_with("pkh("));
+
+        // coin type 1 on testnet
+        let tprv = ExtendedPrivKey {
+            network: Network::Testnet,
+            ..xprv
+        };
+        let accounts = super::accounts(&tprv, 0).unwrap();
+        assert_eq!(accounts["bip84"]["path"], "m/84'/1'/0'");
+        assert_eq!(accounts["bip84"]["xpub"], "[73c5da0a/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M");
+    }
+}
@@ -0,0 +1,456 @@
+// BSMS: Bitcoin Secure Multisig Setup
+// https://github.com/bitcoin/bips/blob/master/bip-0129.mediawiki
+//
+// Round 1: the coordinator hands a token out, every signer returns a key record signed with
+// its key. Round 2: the coordinator returns a descriptor record of the verified keys, every
+// signer checks it holds its key and derives the same first address. Records are encrypted
+// with the token unless it is `00`.
+
+use std::str::FromStr;
+
+use crate::account::origin_key;
+use crate::error::Error;
+use crate::secure::{Xprv, Zeroizing};
+use crate::util::{verify_checksum, xpub};
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use aes::Aes256;
+use base64::{engine::general_purpose, Engine};
+use bdk::bitcoin::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey, KeySource};
+use bdk::bitcoin::hashes::hex::FromHex;
+use bdk::bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bdk::bitcoin::hashes::{sha256, sha512, Hash, HashEngine};
+use bdk::bitcoin::secp256k1::{Message, Secp256k1};
+use bdk::bitcoin::sign_message::{signed_msg_hash, MessageSignature};
+use bdk::bitcoin::{Network, PublicKey};
+use bdk::descriptor::{Descriptor, DescriptorPublicKey};
+use bdk::miniscript::descriptor::checksum::desc_checksum;
+use rand::{thread_rng, Rng};
+
+pub const VERSION: &str = "BSMS 1.0";
+
+/// Token of a session without encryption
+pub const NO_ENCRYPTION: &str = "00";
+
+/// The only path restrictions of the descriptor records, receive and change
+pub const PATH_RESTRICTIONS: &str = "/0/*,/1/*";
+
+const SALT: &[u8] = b"No SPOF";
+const ITERATIONS: u32 = 2048;
+
+/// New session token of 64 (standard) or 128 (extended) bits in hex, `00` for 0 bits
+pub fn token(bits: u32) -> Result<String, Error> {
+    match bits {
+        0 => Ok(NO_ENCRYPTION.to_string()),
+        64 | 128 => {
+            let mut token = Zeroizing::new(vec![0u8; bits as usize / 8]);
+            thread_rng().fill(&mut token[..]);
+            Ok(hex(&token))
+        }
+        bits => Err(Error::Input(format!(
+            "Invalid token size {} bits, expected 0, 64 or 128",
+            bits
+        ))),
+    }
+}
+
+// binary token, none for a session without encryption
+fn token_bytes(token: &str) -> Result<Option<Zeroizing<Vec<u8>>>, Error> {
+    match token {
+        NO_ENCRYPTION => Ok(None),
+        token if token.len() == 16 || token.len() == 32 => Vec::<u8>::from_hex(token)
+            .map(|token| Some(Zeroizing::new(token)))
+            .map_err(|e| Error::Parse(format!("Invalid BSMS token {}: {}", token, e))),
+        token => Err(Error::Parse(format!(
+            "Invalid BSMS token {}, expected 00 or 16 or 32 hex digits",
+            token
+        ))),
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    engine.input(data);
+    Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut engine = HmacEngine::<sha512::Hash>::new(key);
+    engine.input(data);
+    Hmac::<sha512::Hash>::from_engine(engine).to_byte_array()
+}
+
+// first block of PBKDF2-HMAC-SHA512, enough for the 32 bytes key
+fn pbkdf2_sha512(password: &[u8], salt: &[u8], iterations: u32) -> Zeroizing<[u8; 64]> {
+    let mut u = hmac_sha512(password, &[salt, &1u32.to_be_bytes()].concat());
+    let mut t = Zeroizing::new(u);
+    for _ in 1..iterations {
+        u = hmac_sha512(password, &u);
+        t.iter_mut().zip(u.iter()).for_each(|(t, u)| *t ^= u);
+    }
+    t
+}
+
+// PBKDF2-HMAC-SHA512 of the binary token, salted with "No SPOF"
+fn encryption_key(token: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(&pbkdf2_sha512(token, SALT, ITERATIONS)[..32]);
+    key
+}
+
+// AES-256-CTR keyed by the token, the MAC of the token and the record is also the IV
+fn cipher(token: &[u8], record: &[u8]) -> (Zeroizing<[u8; 32]>, [u8; 32]) {
+    let key = encryption_key(token);
+    let mac_key = sha256::Hash::hash(&key[..]).to_byte_array();
+    let mac = hmac_sha256(&mac_key, &[token, record].concat());
+
+    (key, mac)
+}
+
+fn apply_keystream(key: &[u8; 32], mac: &[u8; 32], data: &mut [u8]) {
+    ctr::Ctr128BE::<Aes256>::new(key.into(), mac[..16].into()).apply_keystream(data);
+}
+
+/// Encrypts a record as the hex of its MAC and ciphertext, kept in clear without encryption
+pub fn encrypt(token: &str, record: &str) -> Result<String, Error> {
+    let token = match token_bytes(token)? {
+        Some(token) => token,
+        None => return Ok(record.to_string()),
+    };
+
+    let (key, mac) = cipher(&token, record.as_bytes());
+    let mut data = Zeroizing::new(record.as_bytes().to_vec());
+    apply_keystream(&key, &mac, &mut data);
+
+    Ok(hex(&[&mac[..], &data].concat()))
+}
+
+/// Decrypts a record, checking its MAC
+pub fn decrypt(token: &str, data: &str) -> Result<Zeroizing<String>, Error> {
+    let data = data.trim();
+    let token = match token_bytes(token)? {
+        Some(token) => token,
+        None => return Ok(Zeroizing::new(data.to_string())),
+    };
+
+    let data = Vec::<u8>::from_hex(data)
+        .map_err(|e| Error::Parse(format!("Invalid encrypted BSMS record: {}", e)))?;
+    if data.len() <= 32 {
+        return Err(Error::Parse("Encrypted BSMS record too short".to_string()));
+    }
+    let (mac, ciphertext) = data.split_at(32);
+
+    let key = encryption_key(&token);
+    let mut record = Zeroizing::new(ciphertext.to_vec());
+    let mut iv = [0u8; 32];
+    iv.copy_from_slice(mac);
+    apply_keystream(&key, &iv, &mut record);
+
+    if cipher(&token, &record).1 != iv {
+        return Err(Error::Checksum(
+            "BSMS record MAC mismatch, wrong token or altered record".to_string(),
+        ));
+    }
+
+    String::from_utf8(record.to_vec())
+        .map(Zeroizing::new)
+        .map_err(|e| Error::Parse(format!("Invalid BSMS record: {}", e)))
+}
+
+fn lines(record: &str, count: usize) -> Result<Vec<&str>, Error> {
+    let lines: Vec<&str> = record.trim().lines().map(str::trim).collect();
+    if lines.len() != count || lines[0] != VERSION {
+        return Err(Error::Parse(format!(
+            "Invalid BSMS record, expected {} lines starting with {}",
+            count, VERSION
+        )));
+    }
+
+    Ok(lines)
+}
+
+/// Round 1 record of a signer, its key signed with the key itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRecord {
+    pub token: String,
+    /// Key with its origin, e.g. `[73c5da0a/48'/0'/0'/2']xpub...`
+    pub key: String,
+    pub description: String,
+    /// Bitcoin signed message of the first four lines, in base64
+    pub signature: String,
+}
+
+impl KeyRecord {
+    /// Key record of the `path` key of a master key
+    pub fn new(
+        token: &str,
+        xprv: &ExtendedPrivKey,
+        path: &DerivationPath,
+        description: &str,
+    ) -> Result<Self, Error> {
+        token_bytes(token)?;
+        if description.contains('\n') {
+            return Err(Error::Input(
+                "The description must be a single line".to_string(),
+            ));
+        }
+
+        let secp = Secp256k1::new();
+        let derived = Xprv(xprv.derive_priv(&secp, path)?);
+        let xpub = ExtendedPubKey::from_priv(&secp, &derived);
+
+        let mut record = KeyRecord {
+            token: token.to_string(),
+            key: origin_key(
+                &xprv.fingerprint(&secp).to_string(),
+                path,
+                &xpub.to_string(),
+            ),
+            description: description.to_string(),
+            signature: String::new(),
+        };
+        let hash = signed_msg_hash(&record.message());
+        let message = Message::from_slice(hash.as_byte_array())?;
+        let signature = secp.sign_ecdsa_recoverable(&message, &derived.private_key);
+        record.signature =
+            general_purpose::STANDARD.encode(MessageSignature::new(signature, true).serialize());
+
+        Ok(record)
+    }
+
+    pub fn parse(record: &str) -> Result<Self, Error> {
+        let lines = lines(record, 5)?;
+
+        Ok(KeyRecord {
+            token: lines[1].to_string(),
+            key: lines[2].to_string(),
+            description: lines[3].to_string(),
+            signature: lines[4].to_string(),
+        })
+    }
+
+    fn message(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}",
+            VERSION, self.token, self.key, self.description
+        )
+    }
+
+    /// Checks the token and the signature of the record, returns its key and origin
+    pub fn verify(&self, token: &str) -> Result<(KeySource, ExtendedPubKey), Error> {
+        if self.token != token {
+            return Err(Error::Input(format!(
+                "The key record of {} is for the token {}, not {}",
+                self.key, self.token, token
+            )));
+        }
+        let (origin, xpub) = xpub(&self.key)?;
+        let origin = origin.ok_or_else(|| {
+            Error::Parse(format!(
+                "The key {} has no [fingerprint/path] origin",
+                self.key
+            ))
+        })?;
+
+        let invalid = || {
+            Error::Checksum(format!(
+                "Invalid signature of the key record of {}",
+                self.key
+            ))
+        };
+        let signature = general_purpose::STANDARD
+            .decode(&self.signature)
+            .map_err(|_| invalid())?;
+        let signature = MessageSignature::from_slice(&signature).map_err(|_| invalid())?;
+        let signer = signature
+            .recover_pubkey(
+                &Secp256k1::verification_only(),
+                signed_msg_hash(&self.message()),
+            )
+            .map_err(|_| invalid())?;
+        if signer != PublicKey::new(xpub.public_key) {
+            return Err(invalid());
+        }
+
+        Ok((origin, xpub))
+    }
+}
+
+impl std::fmt::Display for KeyRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n{}", self.message(), self.signature)
+    }
+}
+
+/// Round 2 record of the coordinator, the descriptor template of the wallet and its first address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorRecord {
+    /// Descriptor with `/**` keys and its checksum
+    pub descriptor: String,
+    pub path_restrictions: String,
+    pub first_address: String,
+}
+
+impl DescriptorRecord {
+    /// Descriptor record of a template with `/**` keys, e.g. `wsh(sortedmulti(2,[...]xpub.../**,...))`
+    pub fn new(template: &str, network: Network) -> Result<Self, Error> {
+        Ok(DescriptorRecord {
+            descriptor: format!("{}#{}", template, desc_checksum(template)?),
+            path_restrictions: PATH_RESTRICTIONS.to_string(),
+            first_address: first_address(template, network)?,
+        })
+    }
+
+    pub fn parse(record: &str) -> Result<Self, Error> {
+        let lines = lines(record, 4)?;
+
+        Ok(DescriptorRecord {
+            descriptor: lines[1].to_string(),
+            path_restrictions: lines[2].to_string(),
+            first_address: lines[3].to_string(),
+        })
+    }
+
+    fn template(&self) -> &str {
+        self.descriptor
+            .split_once('#')
+            .map_or(self.descriptor.as_str(), |(template, _)| template)
+    }
+
+    /// Checks the checksum, the path restrictions and the first address of the record
+    pub fn verify(&self, network: Network) -> Result<(), Error> {
+        if !self.descriptor.contains('#') {
+            return Err(Error::Parse(
+                "The descriptor of the record has no checksum".to_string(),
+            ));
+        }
+        verify_checksum(&self.descriptor)?;
+        if self.path_restrictions != PATH_RESTRICTIONS {
+            return Err(Error::Parse(format!(
+                "Unsupported path restrictions {}, expected {}",
+                self.path_restrictions, PATH_RESTRICTIONS
+            )));
+        }
+
+        let address = first_address(self.template(), network)?;
+        if address != self.first_address {
+            return Err(Error::Checksum(format!(
+                "The first address of the descriptor is {}, not {}",
+                address, self.first_address
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Receive and change descriptors, with their checksums
+    pub fn descriptors(&self) -> Result<(String, String), Error> {
+        let descriptor = |chain| {
+            Descriptor::<DescriptorPublicKey>::from_str(&expand(self.template(), chain))
+                .map(|descriptor| descriptor.to_string())
+        };
+
+        Ok((descriptor(0)?, descriptor(1)?))
+    }
+}
+
+impl std::fmt::Display for DescriptorRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\n{}\n{}\n{}",
+            VERSION, self.descriptor, self.path_restrictions, self.first_address
+        )
+    }
+}
+
+fn expand(template: &str, chain: u32) -> String {
+    template.replace("/**", &format!("/{}/*", chain))
+}
+
+fn first_address(template: &str, network: Network) -> Result<String, Error> {
+    let descriptor = Descriptor::<DescriptorPublicKey>::from_str(&expand(template, 0))?;
+    let address = descriptor
+        .at_derivation_index(0)
+        .map_err(|e| Error::Parse(e.to_string()))?
+        .address(network)?;
+
+    Ok(address.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bdk::keys::bip39::Mnemonic;
+
+    #[test]
+    fn test_bsms() {
+        let xprv = ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap();
+        let path = DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+
+        let token = token(64).unwrap();
+        assert_eq!(token.len(), 16);
+
+        let record = KeyRecord::new(&token, &xprv, &path, "signer 1").unwrap();
+        assert_eq!(record.key, "[73c5da0a/48'/0'/0'/2']xpub6DkFAXWQ2dHxq2vatrt9qyA3bXYU4ToWQwCHbf5XB2mSTexcHZCeKS1VZYcPoBd5X8yVcbXFHJR9R8UCVpt82VX1VhR28mCyxUFL4r6KFrf");
+
+        // encrypted round trip, then the signature check
+        let encrypted = encrypt(&token, &record.to_string()).unwrap();
+        let decrypted = decrypt(&token, &encrypted).unwrap();
+        let parsed = KeyRecord::parse(&decrypted).unwrap();
+        assert_eq!(parsed, record);
+        assert!(parsed.verify(&token).is_ok());
+
+        let mut altered = parsed.clone();
+        altered.description = String::from("signer 2");
+        assert_eq!(altered.verify(&token).err().unwrap().code(), 4);
+        assert_eq!(
+            decrypt("0011223344556677", &encrypted)
+                .err()
+                .unwrap()
+                .code(),
+            4
+        );
+
+        let template = format!("wsh(sortedmulti(1,{}/**))", record.key);
+        let descriptor = DescriptorRecord::new(&template, Network::Bitcoin).unwrap();
+        let parsed = DescriptorRecord::parse(&descriptor.to_string()).unwrap();
+        assert!(parsed.verify(Network::Bitcoin).is_ok());
+        let (receive, change) = parsed.descriptors().unwrap();
+        assert!(receive.contains("/0/*))#"));
+        assert!(change.contains("/1/*))#"));
+
+        let mut altered = parsed;
+        altered.first_address = String::from("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(altered.verify(Network::Bitcoin).err().unwrap().code(), 4);
+
+        // the key derivation is the PBKDF2-HMAC-SHA512 of BIP39 seeds, with another salt
+        let mnemonic = Mnemonic::parse("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about").unwrap();
+        assert_eq!(
+            pbkdf2_sha512(mnemonic.to_string().as_bytes(), b"mnemonic", 2048)[..],
+            mnemonic.to_seed("")[..]
+        );
+
+        // the BIP129 construction rebuilt from its primitives, for the token of its examples:
+        // the key and the MAC take the binary token, the IV is the first half of the MAC
+        let token = [0xa5, 0x40, 0x44, 0x30, 0x8c, 0xea, 0xc9, 0xb7];
+        let key = pbkdf2_sha512(&token, b"No SPOF", 2048);
+        let mut engine = HmacEngine::<sha256::Hash>::new(&sha256::Hash::hash(&key[..32])[..]);
+        engine.input(&token);
+        engine.input(b"BSMS 1.0");
+        let mac = Hmac::<sha256::Hash>::from_engine(engine);
+
+        let encrypted =
+            Vec::<u8>::from_hex(&encrypt("a54044308ceac9b7", "BSMS 1.0").unwrap()).unwrap();
+        assert_eq!(encrypted[..32], mac[..]);
+        let mut record = encrypted[32..].to_vec();
+        ctr::Ctr128BE::<Aes256>::new((&key[..32]).into(), (&mac[..16]).into())
+            .apply_keystream(&mut record);
+        assert_eq!(record, b"BSMS 1.0");
+    }
+}
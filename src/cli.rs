@@ -1,8 +1,15 @@
 use bdk::bitcoin::bip32::{DerivationPath, ExtendedPrivKey};
 use bdk::bitcoin::Network;
-use clap::builder::{TypedValueParser, PossibleValuesParser};
+use bdk::keys::bip39::Language;
+use bdk::signer::TapLeavesOptions;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::{Args, Parser, Subcommand};
 
+use crate::account::{MultisigType, ScriptType};
+use crate::output::Format;
+use crate::secure::{wipe_xprv, Zeroize};
+use crate::util::{group, language, secret, tap_leaves, LANGUAGES};
+
 #[derive(Debug, Parser)]
 #[command(
     author,
@@ -10,6 +17,15 @@ use clap::{Args, Parser, Subcommand};
     about,
     long_about = None,
     verbatim_doc_comment,
+    after_help = "Errors are printed as JSON on stderr, with the exit codes:
+  1  other failure (e.g. Electrum server unreachable)
+  2  invalid or missing arguments
+  3  malformed key, mnemonic, share, descriptor or PSBT
+  4  checksum mismatch
+  5  network mismatch
+  6  signing failure
+  7  signing policy violation
+  8  output failure",
 )]
 pub struct Cli {
     /// Sets the network
@@ -24,6 +40,23 @@ pub struct Cli {
     )]
     pub network: Network,
 
+    /// Output format, `plain` and `qr` print only the primary value of the command (e.g. the converted key)
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["json", "compact", "table", "plain", "qr"])
+            .try_map(|s| s.parse::<Format>()),
+        value_name = "FORMAT",
+        default_value = "json",
+        global = true,
+    )]
+    pub output: Format,
+
+    /// JSON file of additional SLIP-132 versions, a list of
+    /// `{"name", "version", "network", "kind", "script_type", "multisig"}` objects
+    #[arg(long, value_name = "PATH")]
+    pub slip132: Option<String>,
+
     #[command(subcommand)]
     pub command: CliCommand,
 }
@@ -31,24 +64,56 @@ pub struct Cli {
 // 1st command level
 #[derive(Debug, Subcommand)]
 pub enum CliCommand {
+    /// Descriptor tools
+    Descriptor(Descriptor),
+
     /// Key management
     Key(Key),
 
+    /// Multisig wallets
+    Multisig(Multisig),
+
+    /// PSBT tools
+    Psbt(Psbt),
+
     /// Wallet operations
     Wallet(Wallet),
 }
 
+#[derive(Debug, Args)]
+pub struct Descriptor {
+    #[command(subcommand)]
+    pub command: DescriptorCommand,
+}
+
 #[derive(Debug, Args)]
 pub struct Key {
     #[command(subcommand)]
     pub command: KeyCommand,
 }
 
+#[derive(Debug, Args)]
+pub struct Multisig {
+    #[command(subcommand)]
+    pub command: MultisigCommand,
+}
+
+#[derive(Debug, Args)]
+pub struct Psbt {
+    #[command(subcommand)]
+    pub command: PsbtCommand,
+}
+
 #[derive(Debug, Args)]
 pub struct Wallet {
-    /// Sets the descriptor to use for the external addresses
-    #[arg(short, long, value_parser, value_name = "DESCRIPTOR")]
-    pub descriptor: String,
+    /// Sets the descriptor to use for the external addresses, SLIP-132 keys are accepted, a BIP389 `/<0;1>/*`
+    /// multipath descriptor sets both keychains, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(short, long, value_parser = secret, value_name = "DESCRIPTOR")]
+    pub descriptor: Option<String>,
+
+    /// Sets the descriptor to use for the internal (change) addresses, `-` for stdin, `@path` for a file
+    #[arg(short, long, value_parser = secret, value_name = "DESCRIPTOR")]
+    pub change_descriptor: Option<String>,
 
     /// Sets the Electrum server to use
     #[clap(
@@ -69,24 +134,71 @@ pub struct Wallet {
 }
 
 // 2nd command level
+#[derive(Debug, Subcommand)]
+pub enum DescriptorCommand {
+    /// Change the SLIP-132 keys (e.g. zpub) of a descriptor to xpub/tpub and recompute its checksum
+    Normalize(Normalize),
+}
+
 #[derive(Debug, Subcommand)]
 pub enum KeyCommand {
-    /// Change the version type (e.g. xpub -> zpub)
+    /// Derive deterministic child entropy from a master key (BIP85)
+    Bip85(Bip85),
+
+    /// Change the version type (e.g. xpub -> zpub), or list the valid ones
     Change(Change),
 
+    /// Recover a master extended key from SLIP-39 share mnemonics
+    Combine(Combine),
+
     /// Generate the derivation path
     Derive(Derive),
 
     /// Generates new random seed mnemonic phrase and corresponding master extended key
     Generate(Generate),
 
+    /// Decode an extended key, WIF, public key, descriptor or address
+    Inspect(Inspect),
+
     /// Restore a master extended key from seed backup mnemonic words
     Restore(Restore),
+
+    /// Split a seed into SLIP-39 Shamir share mnemonics
+    Split(Split),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MultisigCommand {
+    /// BSMS (BIP129) coordinator: build the descriptor record from the verified key records of the signers
+    BsmsDescriptor(BsmsDescriptor),
+
+    /// BSMS (BIP129) signer: sign the key record of a key derived from the master key
+    BsmsKey(BsmsKey),
+
+    /// BSMS (BIP129) coordinator: generate the token of a setup session
+    BsmsToken(BsmsToken),
+
+    /// BSMS (BIP129) signer: verify the descriptor record and its first address
+    BsmsVerify(BsmsVerify),
+
+    /// Build the descriptors of a multisig wallet and its Coldcard, Sparrow/Specter and Bitcoin Core exports
+    Create(Create),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PsbtCommand {
+    /// Show the inputs, outputs, fee and risks of a PSBT, and its change outputs with a descriptor
+    Inspect(PsbtInspect),
+    /// Sign the inputs of a PSBT with the keys of a master key found in their BIP32 derivations
+    Sign(PsbtSign),
 }
 
 #[derive(Debug, Subcommand)]
 pub enum WalletCommand {
-    /// Wallet
+    /// List the addresses of an index range with their derivation paths and keys
+    Addresses(Addresses),
+
+    /// Sync the wallet with the Electrum server and return its balance
     Balance(Balance),
 
     /// Sign
@@ -95,25 +207,281 @@ pub enum WalletCommand {
 
 // 3th argument level
 #[derive(Debug, Args)]
-pub struct Change {
-    /// Target format
-    #[arg(short, long, value_name = "FORMAT")]
-    pub format: String,
+pub struct Bip85 {
+    /// Master private key, `-` for stdin, `@path` for a file
+    #[arg(
+        short,
+        long,
+        value_parser = secret.try_map(|s| s.parse::<ExtendedPrivKey>()),
+        value_name = "XPRV",
+        conflicts_with = "mnemonic"
+    )]
+    pub xprv: Option<ExtendedPrivKey>,
+
+    /// Seed mnemonic words of the master key, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(short, long, value_parser = secret, value_name = "MNEMONIC")]
+    pub mnemonic: Option<String>,
+
+    /// Seed password of the mnemonic, `-` for stdin, `@path` for a file, prompted when empty
+    #[arg(short, long, value_parser = secret, value_name = "PASSWORD", conflicts_with = "xprv")]
+    pub password: Option<Option<String>>,
 
-    /// Key source
+    /// Application of the derived entropy
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["bip39", "wif", "xprv", "hex", "base64", "base85"]),
+        value_name = "APPLICATION",
+        default_value = "bip39",
+    )]
+    pub application: String,
+
+    /// Number of words of the derived mnemonic (bip39)
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["12", "15", "18", "21", "24"])
+            .try_map(|s| s.parse::<u32>()),
+        value_name = "NUMBER",
+        default_value_t = 12,
+    )]
+    pub words: u32,
+
+    /// Wordlist of the derived mnemonic (bip39)
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(LANGUAGES)
+            .try_map(|s| language(&s).ok_or("invalid language")),
+        value_name = "LANGUAGE",
+        default_value = "english",
+    )]
+    pub language: Language,
+
+    /// Number of bytes (hex) or characters (base64, base85) of the derived entropy
+    #[arg(long, value_name = "NUMBER")]
+    pub length: Option<u32>,
+
+    /// Child index
+    #[arg(short, long, value_name = "NUMBER", default_value_t = 0)]
+    pub index: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct BsmsDescriptor {
+    /// Token of the session
+    #[arg(short, long, value_name = "TOKEN")]
+    pub token: String,
+
+    /// Key record of a signer, encrypted unless the token is 00, `-` for stdin, `@path` for a file, repeat for every signer
+    #[arg(short, long = "record", value_parser = secret, value_name = "RECORD", required = true)]
+    pub records: Vec<String>,
+
+    /// Number of signatures required to spend
+    #[arg(long, value_name = "NUMBER")]
+    pub threshold: usize,
+
+    /// Script type of the wallet
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["wsh", "sh-wsh", "tr"])
+            .try_map(|s| s.parse::<MultisigType>()),
+        value_name = "SCRIPT_TYPE",
+        default_value = "wsh",
+    )]
+    pub script_type: MultisigType,
+}
+
+#[derive(Debug, Args)]
+pub struct BsmsKey {
+    /// Token of the session, given by the coordinator
+    #[arg(short, long, value_name = "TOKEN")]
+    pub token: String,
+
+    /// Master private key, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(
+        short,
+        long,
+        value_parser = secret.try_map(|s| s.parse::<ExtendedPrivKey>()),
+        value_name = "XPRV"
+    )]
+    pub xprv: Option<ExtendedPrivKey>,
+
+    /// Derivation path of the key [default: BIP48 (BIP87 for tr) account path of the script type]
+    #[arg(short, long, value_name = "PATH")]
+    pub path: Option<DerivationPath>,
+
+    /// Script type of the wallet, sets the default path
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["wsh", "sh-wsh", "tr"])
+            .try_map(|s| s.parse::<MultisigType>()),
+        value_name = "SCRIPT_TYPE",
+        default_value = "wsh",
+    )]
+    pub script_type: MultisigType,
+
+    /// Account index of the default path
+    #[arg(
+        short,
+        long,
+        value_name = "NUMBER",
+        default_value_t = 0,
+        conflicts_with = "path"
+    )]
+    pub account: u32,
+
+    /// Description of the signer in the record
+    #[arg(short, long, value_name = "DESCRIPTION", default_value = "coldbox")]
+    pub description: String,
+}
+
+#[derive(Debug, Args)]
+pub struct BsmsToken {
+    /// Size of the token, 0 for a session without encryption
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["0", "64", "128"])
+            .try_map(|s| s.parse::<u32>()),
+        value_name = "BITS",
+        default_value_t = 64,
+    )]
+    pub bits: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct BsmsVerify {
+    /// Token of the session
+    #[arg(short, long, value_name = "TOKEN")]
+    pub token: String,
+
+    /// Descriptor record, encrypted unless the token is 00, `-` for stdin, `@path` for a file
+    #[arg(short, long, value_parser = secret, value_name = "RECORD")]
+    pub record: String,
+
+    /// Key of the signer with its origin, as in its key record, checked to be part of the descriptor
     #[arg(short, long, value_name = "KEY")]
     pub key: String,
 }
 
+#[derive(Debug, Args)]
+pub struct Change {
+    /// Target format, applied to the keys of the same kind (private or public) in bulk mode
+    #[arg(
+        short,
+        long,
+        value_name = "FORMAT",
+        required_unless_present_any = ["list", "all"]
+    )]
+    pub format: Option<String>,
+
+    /// List the formats the key can be changed to
+    #[arg(short, long, conflicts_with_all = ["format", "all"])]
+    pub list: bool,
+
+    /// Change the key to every format of its network and kind
+    #[arg(short, long, conflicts_with = "format")]
+    pub all: bool,
+
+    /// Key source, `-` for stdin, `@path` for a file; several lines, origin-prefixed keys or
+    /// descriptors are changed in bulk, line by line
+    #[arg(short, long, value_parser = secret, value_name = "KEY")]
+    pub key: String,
+}
+
+#[derive(Debug, Args)]
+pub struct Combine {
+    /// SLIP-39 share mnemonic, `-` for stdin, `@path` for a file (one share per line), repeat for every share [default: prompt]
+    #[arg(short, long = "share", value_parser = secret, value_name = "SHARE")]
+    pub shares: Vec<String>,
+
+    /// Passphrase protecting the shares, `-` for stdin, `@path` for a file, prompted when empty
+    #[arg(long, value_parser = secret, value_name = "PASSPHRASE")]
+    pub passphrase: Option<Option<String>>,
+}
+
+#[derive(Debug, Args)]
+pub struct Create {
+    /// Cosigner extended public key with its origin, as output by `key derive`
+    /// (e.g. "[73c5da0a/48'/0'/0'/2']xpub.../*"), repeat for every cosigner
+    #[arg(short, long = "key", value_name = "KEY", required = true)]
+    pub keys: Vec<String>,
+
+    /// Number of signatures required to spend
+    #[arg(short, long, value_name = "NUMBER")]
+    pub threshold: usize,
+
+    /// Script type, `tr` spends through a multi_a leaf of an unspendable internal key
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["wsh", "sh-wsh", "tr"])
+            .try_map(|s| s.parse::<MultisigType>()),
+        value_name = "SCRIPT_TYPE",
+        default_value = "wsh",
+    )]
+    pub script_type: MultisigType,
+
+    /// Wallet name of the exports
+    #[arg(long, value_name = "NAME", default_value = "coldbox")]
+    pub name: String,
+
+    /// Output only this export: Coldcard multisig file, Sparrow/Specter wallet or Bitcoin Core `importdescriptors` request
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["coldcard", "specter", "core"]),
+        value_name = "EXPORT"
+    )]
+    pub export: Option<String>,
+}
+
 #[derive(Debug, Args)]
 pub struct Derive {
-    /// Derivation path (e.g. "m/84'/0'/0'" or "m/84h/0h/0h")
-    #[arg(short, long, value_name = "PATH", default_value = "m/84'/0'/0'")]
-    pub path: DerivationPath,
+    /// Derivation path (e.g. "m/84'/0'/0'" or "m/84h/0h/0h") [default: account path of the script type]
+    #[arg(short, long, value_name = "PATH")]
+    pub path: Option<DerivationPath>,
+
+    /// Script type of the output descriptors
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["legacy", "nested-segwit", "segwit", "taproot"])
+            .try_map(|s| s.parse::<ScriptType>()),
+        value_name = "SCRIPT_TYPE",
+        default_value = "segwit",
+    )]
+    pub script_type: ScriptType,
+
+    /// Account index of the default path
+    #[arg(
+        short,
+        long,
+        value_name = "NUMBER",
+        default_value_t = 0,
+        conflicts_with = "path"
+    )]
+    pub account: u32,
+
+    /// Private key, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(
+        short,
+        long,
+        value_parser = secret.try_map(|s| s.parse::<ExtendedPrivKey>()),
+        value_name = "XPRV"
+    )]
+    pub xprv: Option<ExtendedPrivKey>,
 
-    /// Private key
-    #[arg(short, long, value_name = "XPRV")]
-    pub xprv: ExtendedPrivKey,
+    /// Extended public key in any SLIP-132 variant, optionally with its origin (e.g. "[73c5da0a/84'/0'/0']zpub...")
+    #[arg(long, value_name = "XPUB", conflicts_with_all = ["xprv", "account"])]
+    pub xpub: Option<String>,
+
+    /// SLIP-132 version of the key derived from `--xpub` (e.g. "xpub") [default: the version of `--xpub`]
+    #[arg(short, long, value_name = "FORMAT", requires = "xpub")]
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -122,31 +490,249 @@ pub struct Generate {
     #[arg(short, long, value_parser, value_name = "NUMBER", default_value_t = 99)]
     pub dicerolls: usize,
 
+    /// Physical d6 dice rolls used as entropy, digits 1-6 (e.g. "3426153..."), `-` for stdin, `@path` for a file,
+    /// at least 50 rolls for 12 words and 100 rolls for 24 words
+    #[arg(
+        short,
+        long,
+        value_parser = secret,
+        value_name = "ROLLS",
+        conflicts_with = "dicerolls"
+    )]
+    pub rolls: Option<String>,
+
     /// Entropy level based on number of random seed mnemonic words
     #[arg(
         short,
         long,
-        value_parser = PossibleValuesParser::new(["12", "24"])
+        value_parser = PossibleValuesParser::new(["12", "15", "18", "21", "24"])
             .try_map(|s| s.parse::<u8>()),
         value_name = "NUMBER",
         default_value_t = 12,
     )]
     pub entropy: u8,
 
-    /// Seed password
-    #[arg(short, long, value_name = "PASSWORD")]
-    pub password: Option<String>,
+    /// Wordlist of the mnemonic phrase
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(LANGUAGES)
+            .try_map(|s| language(&s).ok_or("invalid language")),
+        value_name = "LANGUAGE",
+        default_value = "english",
+    )]
+    pub language: Language,
+
+    /// Seed password, `-` for stdin, `@path` for a file, prompted when empty
+    #[arg(short, long, value_parser = secret, value_name = "PASSWORD")]
+    pub password: Option<Option<String>>,
+
+    /// Encode the master seed as codex32 (BIP93) instead of a mnemonic phrase
+    #[arg(long, conflicts_with_all = ["password", "language"])]
+    pub codex32: bool,
+
+    /// Number of codex32 shares required to recover the seed, 0 for a single secret
+    #[arg(long, value_name = "NUMBER", default_value_t = 0, requires = "codex32")]
+    pub threshold: u8,
+
+    /// Number of codex32 shares [default: threshold]
+    #[arg(long, value_name = "NUMBER", requires = "codex32")]
+    pub shares: Option<u8>,
+
+    /// Codex32 identifier, 4 bech32 characters [default: random]
+    #[arg(long, value_name = "ID", requires = "codex32")]
+    pub identifier: Option<String>,
+
+    /// Also output the BIP44/49/84/86 xpubs and descriptors of this account index
+    #[arg(long, value_name = "NUMBER")]
+    pub account: Option<u32>,
+}
+
+#[derive(Debug, Args)]
+pub struct Inspect {
+    /// Key, descriptor or address, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(short, long, value_parser = secret, value_name = "KEY")]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct Normalize {
+    /// Descriptor, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(short, long, value_parser = secret, value_name = "DESCRIPTOR")]
+    pub descriptor: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct PsbtInspect {
+    /// PSBT in base64, `-` for stdin, `@path` for a file
+    #[arg(short, long, value_parser = secret, value_name = "BASE64_PSBT")]
+    pub psbt: String,
+
+    /// Descriptor of the wallet for the external addresses, SLIP-132 keys are accepted, `-` for stdin, `@path` for a file
+    #[arg(short, long, value_parser = secret, value_name = "DESCRIPTOR")]
+    pub descriptor: Option<String>,
+
+    /// Descriptor of the wallet for the internal (change) addresses, `-` for stdin, `@path` for a file
+    #[arg(short, long, value_parser = secret, value_name = "DESCRIPTOR", requires = "descriptor")]
+    pub change_descriptor: Option<String>,
+
+    /// Number of addresses of each descriptor searched for the outputs of the wallet
+    #[arg(long, value_name = "NUMBER", default_value_t = 1000)]
+    pub lookahead: u32,
+
+    /// Fee rate in sat/vB above which the fee is flagged
+    #[arg(long, value_name = "SAT_PER_VB", default_value_t = 500.0)]
+    pub max_fee_rate: f64,
+
+    /// Share of the spent amount in percent above which the fee is flagged
+    #[arg(long, value_name = "PERCENT", default_value_t = 10.0)]
+    pub max_fee_percent: f64,
+}
+
+#[derive(Debug, Args)]
+pub struct PsbtSign {
+    /// PSBT in base64, `-` for stdin, `@path` for a file
+    #[arg(short, long, value_parser = secret, value_name = "BASE64_PSBT")]
+    pub psbt: String,
+
+    /// Master private key, `-` for stdin, `@path` for a file
+    #[arg(
+        short,
+        long,
+        value_parser = secret.try_map(|s| s.parse::<ExtendedPrivKey>()),
+        value_name = "XPRV",
+        conflicts_with = "mnemonic"
+    )]
+    pub xprv: Option<ExtendedPrivKey>,
+
+    /// Seed mnemonic words of the master key, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(short, long, value_parser = secret, value_name = "MNEMONIC")]
+    pub mnemonic: Option<String>,
+
+    /// Seed password of the mnemonic, `-` for stdin, `@path` for a file, prompted when empty
+    #[arg(long, value_parser = secret, value_name = "PASSWORD", conflicts_with = "xprv")]
+    pub password: Option<Option<String>>,
+
+    /// Refuses to sign the PSBTs breaking the rules of this JSON policy file, the outputs whose
+    /// script is rebuilt from keys derived from the master key are its change
+    #[arg(long, value_name = "PATH")]
+    pub policy: Option<String>,
+
+    /// Trusts the witness_utxo of the segwit v0 inputs without their non_witness_utxo
+    #[arg(long)]
+    pub trust_witness_utxo: bool,
+
+    /// Signs the inputs with a sighash other than SIGHASH_ALL (or DEFAULT for taproot)
+    #[arg(long)]
+    pub allow_all_sighashes: bool,
+
+    /// Only adds the signatures, without finalizing the inputs
+    #[arg(long)]
+    pub no_finalize: bool,
 }
 
 #[derive(Debug, Args)]
 pub struct Restore {
-    /// Seed mnemonic words, must be quoted (eg. "word1 word2 ...")
-    #[arg(short, long, value_name = "MNEMONIC")]
-    pub mnemonic: String,
+    /// Seed mnemonic words, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(
+        short,
+        long,
+        value_parser = secret,
+        value_name = "MNEMONIC",
+        conflicts_with = "codex32"
+    )]
+    pub mnemonic: Option<String>,
+
+    /// Codex32 (BIP93) secret or share, `-` for stdin, `@path` for a file (one share per line), repeat for every share
+    #[arg(
+        long,
+        value_parser = secret,
+        value_name = "SHARE",
+        conflicts_with_all = ["language", "password"]
+    )]
+    pub codex32: Vec<String>,
+
+    /// Wordlist of the mnemonic phrase, detected from the words when not set
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(LANGUAGES)
+            .try_map(|s| language(&s).ok_or("invalid language")),
+        value_name = "LANGUAGE",
+    )]
+    pub language: Option<Language>,
+
+    /// Seed password, `-` for stdin, `@path` for a file, prompted when empty
+    #[arg(short, long, value_parser = secret, value_name = "PASSWORD")]
+    pub password: Option<Option<String>>,
+
+    /// Also output the BIP44/49/84/86 xpubs and descriptors of this account index
+    #[arg(long, value_name = "NUMBER")]
+    pub account: Option<u32>,
+}
+
+#[derive(Debug, Args)]
+pub struct Split {
+    /// Seed mnemonic words to split, `-` for stdin, `@path` for a file [default: prompt]
+    #[arg(
+        short,
+        long,
+        value_parser = secret,
+        value_name = "MNEMONIC",
+        conflicts_with = "secret"
+    )]
+    pub mnemonic: Option<String>,
+
+    /// Seed password of the mnemonic, `-` for stdin, `@path` for a file, prompted when empty
+    #[arg(short, long, value_parser = secret, value_name = "PASSWORD", conflicts_with = "secret")]
+    pub password: Option<Option<String>>,
 
-    /// Seed password
-    #[arg(short, long, value_name = "PASSWORD")]
-    pub password: Option<String>,
+    /// Master secret to split in hex, used as the BIP32 seed, `-` for stdin, `@path` for a file
+    #[arg(short, long, value_parser = secret, value_name = "HEX")]
+    pub secret: Option<String>,
+
+    /// Share group as member threshold and count (e.g. "2of3"), repeat for every group
+    #[arg(
+        short,
+        long = "group",
+        value_parser = group,
+        value_name = "GROUP",
+        default_value = "2of3"
+    )]
+    pub groups: Vec<(u8, u8)>,
+
+    /// Number of groups required to recover the seed
+    #[arg(short = 't', long, value_name = "NUMBER", default_value_t = 1)]
+    pub group_threshold: u8,
+
+    /// Passphrase protecting the shares, `-` for stdin, `@path` for a file, prompted when empty
+    #[arg(long, value_parser = secret, value_name = "PASSPHRASE")]
+    pub passphrase: Option<Option<String>>,
+
+    /// Iteration exponent of the passphrase encryption
+    #[arg(short, long, value_name = "NUMBER", default_value_t = 1)]
+    pub exponent: u8,
+}
+
+#[derive(Debug, Args)]
+pub struct Addresses {
+    /// First address index
+    #[arg(long, value_name = "NUMBER", default_value_t = 0)]
+    pub start: u32,
+
+    /// Number of addresses of each descriptor
+    #[arg(long, value_name = "NUMBER", default_value_t = 20)]
+    pub count: u32,
+
+    /// Output format, `csv` is printed as is and cannot be combined with `--output`
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["json", "csv"]),
+        value_name = "FORMAT",
+        default_value = "json",
+    )]
+    pub format: String,
 }
 
 #[derive(Debug, Args)]
@@ -157,4 +743,140 @@ pub struct Sign {
     /// Sets the PSBT to sign
     #[arg(short, long, value_name = "BASE64_PSBT")]
     pub psbt: String,
+    /// Refuses to sign the PSBTs breaking the rules of this JSON policy file (max_fee, max_fee_rate,
+    /// max_amount, allowed_destinations, sighash_all_only, change_to_wallet)
+    #[arg(long, value_name = "PATH")]
+    pub policy: Option<String>,
+
+    /// Signs only these input indexes (e.g. "0,2"), all the inputs of the wallet by default
+    #[arg(short, long = "input", value_name = "INDEX", value_delimiter = ',')]
+    pub inputs: Vec<usize>,
+
+    /// Trusts the witness_utxo of the segwit v0 inputs without their non_witness_utxo
+    #[arg(long)]
+    pub trust_witness_utxo: bool,
+
+    /// Height assumed to check the timelocks, the nLockTime of the transaction by default
+    #[arg(long, value_name = "HEIGHT")]
+    pub assume_height: Option<u32>,
+
+    /// Signs the inputs with a sighash other than SIGHASH_ALL (or DEFAULT for taproot)
+    #[arg(long)]
+    pub allow_all_sighashes: bool,
+
+    /// Only adds the signatures, without finalizing the inputs
+    #[arg(long)]
+    pub no_finalize: bool,
+
+    /// Does not sign the taproot key path with the internal key
+    #[arg(long)]
+    pub no_tap_internal_key: bool,
+
+    /// Taproot script path leaves to sign: all, none, include:HASH,... or exclude:HASH,...
+    #[arg(long, value_parser = tap_leaves, value_name = "LEAVES", default_value = "all")]
+    pub tap_leaves: TapLeavesOptions,
+
+    /// Does not grind the ECDSA signatures to 70 bytes
+    #[arg(long)]
+    pub no_grinding: bool,
+}
+
+// secrets given as arguments are wiped once the command is done
+impl Drop for Bip85 {
+    fn drop(&mut self) {
+        if let Some(xprv) = &mut self.xprv {
+            wipe_xprv(xprv);
+        }
+        self.mnemonic.zeroize();
+        self.password.zeroize();
+    }
+}
+
+impl Drop for BsmsKey {
+    fn drop(&mut self) {
+        if let Some(xprv) = &mut self.xprv {
+            wipe_xprv(xprv);
+        }
+    }
+}
+
+impl Drop for Change {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl Drop for Combine {
+    fn drop(&mut self) {
+        self.shares.zeroize();
+        self.passphrase.zeroize();
+    }
+}
+
+impl Drop for Derive {
+    fn drop(&mut self) {
+        if let Some(xprv) = &mut self.xprv {
+            wipe_xprv(xprv);
+        }
+    }
+}
+
+impl Drop for Generate {
+    fn drop(&mut self) {
+        self.rolls.zeroize();
+        self.password.zeroize();
+    }
+}
+
+impl Drop for Inspect {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl Drop for Normalize {
+    fn drop(&mut self) {
+        self.descriptor.zeroize();
+    }
+}
+
+impl Drop for PsbtInspect {
+    fn drop(&mut self) {
+        self.descriptor.zeroize();
+        self.change_descriptor.zeroize();
+    }
+}
+
+impl Drop for PsbtSign {
+    fn drop(&mut self) {
+        if let Some(xprv) = &mut self.xprv {
+            wipe_xprv(xprv);
+        }
+        self.mnemonic.zeroize();
+        self.password.zeroize();
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        self.mnemonic.zeroize();
+        self.codex32.zeroize();
+        self.password.zeroize();
+    }
+}
+
+impl Drop for Split {
+    fn drop(&mut self) {
+        self.mnemonic.zeroize();
+        self.password.zeroize();
+        self.secret.zeroize();
+        self.passphrase.zeroize();
+    }
+}
+
+impl Drop for Wallet {
+    fn drop(&mut self) {
+        self.descriptor.zeroize();
+        self.change_descriptor.zeroize();
+    }
 }
@@ -0,0 +1,449 @@
+// Codex32: checksummed SSSS-aware BIP32 seeds
+// https://github.com/bitcoin/bips/blob/master/bip-0093.mediawiki
+
+use crate::error::Error;
+
+use crate::secure::{Zeroize, Zeroizing};
+
+use rand::{thread_rng, Rng};
+
+const CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const HRP: &str = "ms";
+const CHECKSUM_LENGTH: usize = 13;
+const MAX_LENGTH: usize = 93;
+const MS32_CONST: u128 = 0x10ce0795c2fd1e62a;
+
+// share indices in the order shares are dealt, the secret index 's' is never dealt
+const SHARE_INDICES: &str = "acdefghjklmnpqrtuvwxyz023456789";
+
+/// A parsed and checksum-validated codex32 string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub threshold: u8,
+    pub identifier: String,
+    pub index: char,
+    data: Vec<u8>,
+}
+
+impl Share {
+    /// Parses a codex32 string, pointing at likely typos when the checksum fails
+    pub fn from_string(s: &str) -> Result<Share, Error> {
+        if s.chars().any(|c| c.is_ascii_lowercase()) && s.chars().any(|c| c.is_ascii_uppercase()) {
+            return Err(Error::Parse(format!("Mixed case codex32 string {}", s)));
+        }
+        let s = s.to_lowercase();
+
+        let data_part = s
+            .strip_prefix(HRP)
+            .and_then(|s| s.strip_prefix('1'))
+            .ok_or_else(|| {
+                Error::Parse(format!("Invalid codex32 prefix in {}, expected ms1", s))
+            })?;
+
+        if s.len() > MAX_LENGTH || data_part.len() < 6 + CHECKSUM_LENGTH + 26 {
+            return Err(Error::Parse(format!(
+                "Invalid codex32 string length {}",
+                s.len()
+            )));
+        }
+
+        let data = data_part
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                CHARSET.find(c).map(|v| v as u8).ok_or_else(|| {
+                    Error::Parse(format!(
+                        "Invalid codex32 character '{}' at position {}",
+                        c,
+                        i + HRP.len() + 1
+                    ))
+                })
+            })
+            .collect::<Result<Vec<u8>, Error>>()?;
+
+        if polymod(&data) != MS32_CONST {
+            return Err(Error::Checksum(format!(
+                "Invalid codex32 checksum in {}{}",
+                s,
+                error_hints(&data)
+            )));
+        }
+
+        let threshold = data_part.as_bytes()[0];
+        let index = data_part.as_bytes()[5] as char;
+        let threshold = match threshold {
+            b'0' => 0,
+            b'2'..=b'9' => threshold - b'0',
+            _ => {
+                return Err(Error::Parse(format!(
+                    "Invalid codex32 threshold '{}'",
+                    threshold as char
+                )))
+            }
+        };
+        if threshold == 0 && index != 's' {
+            return Err(Error::Parse(
+                "A codex32 string with threshold 0 must have share index 's'".to_string(),
+            ));
+        }
+
+        Ok(Share {
+            threshold,
+            identifier: data_part[1..5].to_string(),
+            index,
+            data,
+        })
+    }
+
+    /// Payload bytes of the share, the master seed for the 's' share
+    pub fn payload(&self) -> Vec<u8> {
+        let payload = &self.data[6..self.data.len() - CHECKSUM_LENGTH];
+        let bits: Vec<bool> = payload
+            .iter()
+            .flat_map(|v| (0..5).rev().map(move |i| (v >> i) & 1 == 1))
+            .collect();
+
+        // the incomplete group at the end is padding
+        bits.chunks_exact(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, bit| (acc << 1) | *bit as u8))
+            .collect()
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
+impl std::fmt::Display for Share {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let data: String = self
+            .data
+            .iter()
+            .map(|v| CHARSET.as_bytes()[*v as usize] as char)
+            .collect();
+        write!(f, "{}1{}", HRP, data)
+    }
+}
+
+/// Encodes a master seed as a codex32 secret, or as `count` shares of which
+/// `threshold` recover it
+pub fn generate(
+    seed: &[u8],
+    threshold: u8,
+    count: u8,
+    identifier: Option<&str>,
+) -> Result<Vec<Share>, Error> {
+    if !(16..=32).contains(&seed.len()) {
+        return Err(Error::Input(
+            "The master seed must be between 16 and 32 bytes".to_string(),
+        ));
+    }
+    if threshold == 1 || threshold > 9 {
+        return Err(Error::Input(
+            "The codex32 threshold must be 0 or between 2 and 9".to_string(),
+        ));
+    }
+    if threshold > 0 && (count < threshold || count as usize > SHARE_INDICES.len()) {
+        return Err(Error::Input(format!(
+            "The number of codex32 shares must be between the threshold and {}",
+            SHARE_INDICES.len()
+        )));
+    }
+
+    let identifier = match identifier {
+        Some(id) => {
+            let id = id.to_lowercase();
+            if id.len() != 4 || !id.chars().all(|c| CHARSET.contains(c)) {
+                return Err(Error::Input(format!(
+                    "Invalid codex32 identifier {}, expected 4 bech32 characters",
+                    id
+                )));
+            }
+            id
+        }
+        None => {
+            let mut rng = thread_rng();
+            (0..4)
+                .map(|_| CHARSET.as_bytes()[rng.gen_range(0..32)] as char)
+                .collect()
+        }
+    };
+
+    let header = format!("{}{}", (b'0' + threshold) as char, identifier);
+    let secret = encode(&header, 's', &seed_to_values(seed));
+    if threshold == 0 {
+        return Ok(vec![secret]);
+    }
+
+    // k - 1 random shares plus the secret define the polynomial
+    let mut rng = thread_rng();
+    let mut shares: Vec<Share> = SHARE_INDICES
+        .chars()
+        .take(threshold as usize - 1)
+        .map(|index| {
+            let mut random = vec![0u8; seed.len()];
+            rng.fill(&mut random[..]);
+            encode(&header, index, &seed_to_values(&random))
+        })
+        .collect();
+
+    let mut points = shares.clone();
+    points.push(secret);
+    for index in SHARE_INDICES
+        .chars()
+        .skip(threshold as usize - 1)
+        .take((count - threshold + 1) as usize)
+    {
+        shares.push(interpolate(&points, index));
+    }
+
+    Ok(shares)
+}
+
+/// Recovers the master seed from a codex32 secret or enough shares
+pub fn recover(strings: &[String]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let shares = strings
+        .iter()
+        .map(|s| Share::from_string(s))
+        .collect::<Result<Vec<Share>, Error>>()?;
+
+    let first = shares
+        .first()
+        .cloned()
+        .ok_or_else(|| Error::Input("No codex32 strings given".to_string()))?;
+
+    if let Some(secret) = shares.iter().find(|share| share.index == 's') {
+        return Ok(Zeroizing::new(secret.payload()));
+    }
+
+    for share in &shares {
+        if share.threshold != first.threshold
+            || share.identifier != first.identifier
+            || share.data.len() != first.data.len()
+        {
+            return Err(Error::Parse(
+                "All codex32 shares must have the same threshold, identifier and length"
+                    .to_string(),
+            ));
+        }
+    }
+
+    let mut distinct: Vec<Share> = vec![];
+    for share in shares {
+        if !distinct.iter().any(|s| s.index == share.index) {
+            distinct.push(share);
+        }
+    }
+    if distinct.len() < first.threshold as usize {
+        return Err(Error::Input(format!(
+            "Insufficient codex32 shares, {} are required and {} were given",
+            first.threshold,
+            distinct.len()
+        )));
+    }
+
+    Ok(Zeroizing::new(
+        interpolate(&distinct[..first.threshold as usize], 's').payload(),
+    ))
+}
+
+fn seed_to_values(seed: &[u8]) -> Vec<u8> {
+    let bits: Vec<bool> = seed
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+        .collect();
+    bits.chunks(5)
+        .map(|chunk| {
+            (0..5).fold(0u8, |acc, i| {
+                (acc << 1) | chunk.get(i).copied().unwrap_or(false) as u8
+            })
+        })
+        .collect()
+}
+
+fn encode(header: &str, index: char, payload: &[u8]) -> Share {
+    let mut data: Vec<u8> = header
+        .chars()
+        .chain([index])
+        .map(|c| CHARSET.find(c).unwrap() as u8)
+        .chain(payload.iter().copied())
+        .collect();
+
+    let checksum = polymod(&[&data[..], &[0; CHECKSUM_LENGTH]].concat()) ^ MS32_CONST;
+    data.extend((0..CHECKSUM_LENGTH).map(|i| ((checksum >> (5 * (12 - i))) & 31) as u8));
+
+    Share {
+        threshold: header.as_bytes()[0] - b'0',
+        identifier: header[1..].to_string(),
+        index,
+        data,
+    }
+}
+
+// BCH checksum over GF(32)
+fn polymod(values: &[u8]) -> u128 {
+    const GEN: [u128; 5] = [
+        0x19dc500ce73fde210,
+        0x1bfae00def77fe529,
+        0x1fbd920fffe7bee52,
+        0x1739640bdeee3fdad,
+        0x07729a039cfc75f5a,
+    ];
+
+    values.iter().fold(0x23181b3, |residue, v| {
+        let b = residue >> 60;
+        let residue = ((residue & 0x0fffffffffffffff) << 5) ^ u128::from(*v);
+        (0..5).fold(residue, |residue, i| {
+            if (b >> i) & 1 == 1 {
+                residue ^ GEN[i]
+            } else {
+                residue
+            }
+        })
+    })
+}
+
+// Single character substitutions that give a valid checksum
+fn error_hints(data: &[u8]) -> String {
+    let mut hints = vec![];
+    let mut candidate = data.to_vec();
+    for i in 0..data.len() {
+        for v in 0..32u8 {
+            if v == data[i] {
+                continue;
+            }
+            candidate[i] = v;
+            if polymod(&candidate) == MS32_CONST {
+                hints.push(format!(
+                    "position {}: '{}' -> '{}'",
+                    i + HRP.len() + 1,
+                    CHARSET.as_bytes()[data[i] as usize] as char,
+                    CHARSET.as_bytes()[v as usize] as char
+                ));
+            }
+        }
+        candidate[i] = data[i];
+    }
+
+    if hints.is_empty() {
+        ", more than one character is wrong".to_string()
+    } else {
+        format!(", possible error at {}", hints.join(", "))
+    }
+}
+
+// GF(32) multiplication modulo x^5 + x^3 + 1
+fn gf_mul(a: u8, b: u8) -> u8 {
+    (0..5).fold(0u8, |acc, i| {
+        let acc = if acc & 0x10 != 0 {
+            ((acc << 1) ^ 0x29) & 31
+        } else {
+            acc << 1
+        };
+        if (b >> (4 - i)) & 1 == 1 {
+            acc ^ a
+        } else {
+            acc
+        }
+    })
+}
+
+fn gf_inv(a: u8) -> u8 {
+    (1..32).find(|b| gf_mul(a, *b) == 1).unwrap_or(0)
+}
+
+// Lagrange interpolation of the shares at the given share index
+fn interpolate(shares: &[Share], index: char) -> Share {
+    let x = CHARSET.find(index).unwrap() as u8;
+    if let Some(share) = shares.iter().find(|share| share.index == index) {
+        return share.clone();
+    }
+
+    let xs: Vec<u8> = shares
+        .iter()
+        .map(|share| CHARSET.find(share.index).unwrap() as u8)
+        .collect();
+
+    let mut data = vec![0u8; shares[0].data.len()];
+    for (i, share) in shares.iter().enumerate() {
+        let weight = xs
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .fold(1u8, |acc, (_, xj)| {
+                gf_mul(acc, gf_mul(x ^ xj, gf_inv(xs[i] ^ xj)))
+            });
+        for (d, v) in data.iter_mut().zip(&share.data) {
+            *d ^= gf_mul(weight, *v);
+        }
+    }
+
+    Share {
+        threshold: shares[0].threshold,
+        identifier: shares[0].identifier.clone(),
+        index,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0093.mediawiki#test-vectors
+
+    #[test]
+    fn test_codex32() {
+        // test vector 1
+        let secret = recover(&[String::from(
+            "ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlw",
+        )])
+        .unwrap();
+        assert_eq!(
+            *secret,
+            [
+                0x31, 0x8c, 0x63, 0x18, 0xc6, 0x31, 0x8c, 0x63, 0x18, 0xc6, 0x31, 0x8c, 0x63, 0x18,
+                0xc6, 0x31
+            ]
+        );
+
+        // test vector 2
+        let shares = [
+            String::from("MS12NAMEA320ZYXWVUTSRQPNMLKJHGFEDCAXRPP870HKKQRM"),
+            String::from("MS12NAMECACDEFGHJKLMNPQRSTUVWXYZ023FTR2GDZMPY6PN"),
+        ];
+        let secret = recover(&shares).unwrap();
+        assert_eq!(
+            *secret,
+            [
+                0xd1, 0x80, 0x8e, 0x09, 0x6b, 0x35, 0xb2, 0x09, 0xca, 0x12, 0x13, 0x2b, 0x26, 0x46,
+                0x62, 0xa5
+            ]
+        );
+        let parsed: Vec<Share> = shares
+            .iter()
+            .map(|s| Share::from_string(s).unwrap())
+            .collect();
+        assert_eq!(
+            interpolate(&parsed, 's').to_string(),
+            "ms12names6xqguzttxkeqnjsjzv4jv3nz5k3kwgsphuh6evw"
+        );
+
+        // a typo is located
+        let err = recover(&[String::from(
+            "ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlx",
+        )])
+        .unwrap_err();
+        assert!(err.to_string().contains("position 47: 'x' -> 'w'"));
+
+        // 3 of 5 round trip
+        let shares = generate(&secret, 3, 5, Some("c0de")).unwrap();
+        assert_eq!(shares.len(), 5);
+        let strings: Vec<String> = [4, 0, 2].iter().map(|i| shares[*i].to_string()).collect();
+        assert!(strings[0].starts_with("ms13c0de"));
+        assert_eq!(recover(&strings).unwrap(), secret);
+        assert!(recover(&strings[..2]).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::cli::Normalize;
+use crate::commands::key_change::change_keys;
+use crate::commands::Primary;
+use crate::error::Error;
+use crate::secure::Zeroizing;
+use crate::slip132::registry;
+use crate::util::{descriptor_script, required, verify_checksum};
+
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::descriptor::Descriptor;
+use bdk::miniscript::descriptor::checksum::desc_checksum;
+
+use serde_json::json;
+
+/// A descriptor with its SLIP-132 keys changed to the plain BIP32 versions
+pub struct Normalized {
+    /// Normalized descriptor, with its recomputed checksum
+    pub descriptor: Zeroizing<String>,
+    pub checksum: String,
+    pub script_type: &'static str,
+    /// Versions of the changed keys, e.g. `{"from": "zpub", "to": "xpub", "script_type": "p2wpkh"}`
+    pub keys: Vec<serde_json::Value>,
+    /// Versions implying another script type than the descriptor's
+    pub warnings: Vec<String>,
+}
+
+/// Changes the SLIP-132 keys (e.g. zpub, Vpub) of a descriptor to xpub/tpub (xprv/tprv),
+/// the only versions the descriptor parser accepts
+pub fn normalize(descriptor: &str) -> Result<Normalized, Error> {
+    let descriptor = descriptor.trim();
+    verify_checksum(descriptor)?;
+    let body = descriptor
+        .split_once('#')
+        .map_or(descriptor, |(body, _)| body);
+
+    let registry = registry();
+    let mut versions = vec![];
+    let body = Zeroizing::new(change_keys(body, |key, version| {
+        let standard = registry.standard(version);
+        if standard.name == version.name {
+            return Ok(None);
+        }
+        versions.push((version.clone(), standard.name.clone()));
+        registry.to_standard(key).map(Some)
+    })?);
+
+    let (parsed, _) = Descriptor::parse_descriptor(&Secp256k1::new(), &body)?;
+    let script_type = descriptor_script(parsed.desc_type());
+
+    let mut keys = vec![];
+    let mut warnings = vec![];
+    for (version, standard) in versions {
+        keys.push(json!({
+            "from": version.name,
+            "to": standard,
+            "script_type": version.script_type,
+        }));
+        if version.script_type != script_type {
+            warnings.push(format!(
+                "{} implies a {} script but the descriptor is {}",
+                version.name, version.script_type, script_type
+            ));
+        }
+    }
+    warnings.dedup();
+
+    let checksum = desc_checksum(&body)?;
+    let descriptor = Zeroizing::new(format!("{}#{}", *body, checksum));
+
+    Ok(Normalized {
+        descriptor,
+        checksum,
+        script_type,
+        keys,
+        warnings,
+    })
+}
+
+impl Normalize {
+    pub fn normalize(&self) -> Result<serde_json::Value, Error> {
+        let descriptor = required(&self.descriptor, "descriptor")?;
+        let normalized = normalize(&descriptor)?;
+
+        Ok(json!({
+            "descriptor": *normalized.descriptor,
+            "checksum": normalized.checksum,
+            "script_type": normalized.script_type,
+            "keys": normalized.keys,
+            "warnings": normalized.warnings,
+        }))
+    }
+}
+
+impl Primary for Normalize {
+    fn primary(&self) -> &str {
+        "descriptor"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki#test-vectors
+        let normalize_cmd = Normalize {
+            descriptor: Some(String::from("wpkh([73c5da0a/84'/0'/0']zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs/0/*)")),
+        };
+        let result = normalize_cmd.normalize().unwrap();
+        let body = "wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)";
+        let checksum = desc_checksum(body).unwrap();
+        assert_eq!(result["descriptor"], format!("{}#{}", body, checksum));
+        assert_eq!(result["checksum"], checksum);
+        assert_eq!(result["keys"][0]["from"], "zpub");
+        assert_eq!(result["keys"][0]["to"], "xpub");
+        assert_eq!(result["warnings"].as_array().unwrap().len(), 0);
+
+        // a zpub wrapped as nested segwit
+        let normalized = normalize("sh(wpkh(zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs/0/*))").unwrap();
+        assert_eq!(normalized.script_type, "p2sh-p2wpkh");
+        assert_eq!(
+            normalized.warnings,
+            vec!["zpub implies a p2wpkh script but the descriptor is p2sh-p2wpkh"]
+        );
+
+        // plain keys are kept, the checksum is still added
+        let normalized = normalize(body).unwrap();
+        assert_eq!(*normalized.descriptor, format!("{}#{}", body, checksum));
+        assert!(normalized.keys.is_empty());
+
+        // the checksum of the original descriptor is verified
+        let e = normalize(&format!("{}#aaaaaaaa", body)).err().unwrap();
+        assert_eq!(e.code(), 4);
+    }
+}
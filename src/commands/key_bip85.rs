@@ -0,0 +1,251 @@
+use crate::cli::Bip85;
+use crate::commands::Primary;
+use crate::secure::{Xprv, Zeroizing};
+use crate::util::{passphrase, required};
+
+use crate::error::Error;
+use base64::{engine::general_purpose, Engine};
+use bdk::bitcoin::bip32::{ChainCode, ChildNumber, DerivationPath, ExtendedPrivKey, Fingerprint};
+use bdk::bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bdk::bitcoin::hashes::{sha512, Hash, HashEngine};
+use bdk::bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bdk::bitcoin::{Network, PrivateKey};
+use bdk::keys::bip39::{Language, Mnemonic};
+
+use serde_json::json;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0085.mediawiki
+
+const BIP85_PURPOSE: u32 = 83696968;
+
+const BASE85_CHARS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&()*+-;<=>?@^_`{|}~";
+
+fn language_code(language: Language) -> u32 {
+    match language {
+        Language::English => 0,
+        Language::Japanese => 1,
+        Language::Korean => 2,
+        Language::Spanish => 3,
+        Language::SimplifiedChinese => 4,
+        Language::TraditionalChinese => 5,
+        Language::French => 6,
+        Language::Italian => 7,
+        Language::Czech => 8,
+    }
+}
+
+// RFC 1924 base85, as produced by Python's base64.b85encode
+fn base85(data: &[u8]) -> String {
+    data.chunks(4)
+        .flat_map(|chunk| {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            let mut value = u32::from_be_bytes(word);
+            let mut chars = [0u8; 5];
+            for c in chars.iter_mut().rev() {
+                *c = BASE85_CHARS[(value % 85) as usize];
+                value /= 85;
+            }
+            chars.into_iter().take(chunk.len() + 1)
+        })
+        .map(char::from)
+        .collect()
+}
+
+fn check_length(length: Option<u32>, min: u32, max: u32) -> Result<u32, Error> {
+    match length {
+        Some(length) if (min..=max).contains(&length) => Ok(length),
+        _ => Err(Error::Input(format!(
+            "The length must be between {} and {}",
+            min, max
+        ))),
+    }
+}
+
+impl Bip85 {
+    pub fn bip85(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let secp = Secp256k1::new();
+
+        let xprv = match &self.xprv {
+            Some(xprv) => {
+                if xprv.network != network {
+                    return Err(Error::network_mismatch(xprv.network, network));
+                }
+                Xprv(*xprv)
+            }
+            None => {
+                let mnemonic = required(&self.mnemonic, "mnemonic")?;
+                let mnemonic = Mnemonic::parse(mnemonic.as_str())?;
+                let password = passphrase(&self.password, "password")?;
+                let seed = Zeroizing::new(mnemonic.to_seed(password.as_str()));
+                Xprv(ExtendedPrivKey::new_master(network, &*seed)?)
+            }
+        };
+
+        let application: Vec<u32> = match self.application.as_str() {
+            "bip39" => vec![39, language_code(self.language), self.words],
+            "wif" => vec![2],
+            "xprv" => vec![32],
+            "hex" => vec![128169, check_length(self.length, 16, 64)?],
+            "base64" => vec![707764, check_length(self.length, 20, 86)?],
+            "base85" => vec![707785, check_length(self.length, 10, 80)?],
+            app => return Err(Error::Input(format!("Unknown application {}", app))),
+        };
+
+        let path: DerivationPath = [BIP85_PURPOSE]
+            .iter()
+            .chain(application.iter())
+            .chain([self.index].iter())
+            .map(|i| ChildNumber::from_hardened_idx(*i))
+            .collect::<Result<Vec<ChildNumber>, _>>()?
+            .into();
+
+        let derived = Xprv(xprv.derive_priv(&secp, &path)?);
+
+        let mut engine = HmacEngine::<sha512::Hash>::new(b"bip-entropy-from-k");
+        engine.input(&derived.private_key.secret_bytes());
+        let entropy = Zeroizing::new(Hmac::<sha512::Hash>::from_engine(engine).to_byte_array());
+
+        let (name, value) = match self.application.as_str() {
+            "bip39" => {
+                let len = self.words as usize * 4 / 3;
+                let mnemonic = Mnemonic::from_entropy_in(self.language, &entropy[..len])?;
+                ("mnemonic", mnemonic.to_string())
+            }
+            "wif" => {
+                let key = SecretKey::from_slice(&entropy[..32])?;
+                ("wif", PrivateKey::new(key, network).to_wif())
+            }
+            "xprv" => {
+                let mut chain_code = Zeroizing::new([0u8; 32]);
+                chain_code.copy_from_slice(&entropy[..32]);
+                let child = Xprv(ExtendedPrivKey {
+                    network,
+                    depth: 0,
+                    parent_fingerprint: Fingerprint::default(),
+                    child_number: ChildNumber::from_normal_idx(0)?,
+                    private_key: SecretKey::from_slice(&entropy[32..])?,
+                    chain_code: ChainCode::from(*chain_code),
+                });
+                ("xprv", child.to_string())
+            }
+            "hex" => {
+                let len = application[1] as usize;
+                let hex = entropy[..len]
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                ("hex", hex)
+            }
+            "base64" => {
+                let len = application[1] as usize;
+                let password = Zeroizing::new(general_purpose::STANDARD.encode(entropy));
+                ("password", password[..len].to_string())
+            }
+            _ => {
+                let len = application[1] as usize;
+                (
+                    "password",
+                    Zeroizing::new(base85(&entropy[..]))[..len].to_string(),
+                )
+            }
+        };
+
+        Ok(json!({
+            "fingerprint": xprv.fingerprint(&secp).to_string(),
+            "path": path.to_string(),
+            name: value
+        }))
+    }
+}
+
+impl Primary for Bip85 {
+    fn primary(&self) -> &str {
+        match self.application.as_str() {
+            "bip39" => "mnemonic",
+            "wif" => "wif",
+            "xprv" => "xprv",
+            "hex" => "hex",
+            _ => "password",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn bip85_cmd(application: &str, words: u32, length: Option<u32>) -> Bip85 {
+        Bip85 {
+            xprv: Some(ExtendedPrivKey::from_str("xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb").unwrap()),
+            mnemonic: None,
+            password: None,
+            application: String::from(application),
+            words,
+            language: Language::English,
+            length,
+            index: 0,
+        }
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0085.mediawiki#applications
+
+    #[test]
+    fn test_bip85() {
+        let result = bip85_cmd("bip39", 12, None)
+            .bip85(Network::Bitcoin)
+            .unwrap();
+        assert_eq!(result.get("path").unwrap(), "m/83696968'/39'/0'/12'/0'");
+        assert_eq!(
+            result.get("mnemonic").unwrap(),
+            "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose"
+        );
+
+        let result = bip85_cmd("bip39", 18, None)
+            .bip85(Network::Bitcoin)
+            .unwrap();
+        assert_eq!(result.get("mnemonic").unwrap(), "near account window bike charge season chef number sketch tomorrow excuse sniff circle vital hockey outdoor supply token");
+
+        let result = bip85_cmd("bip39", 24, None)
+            .bip85(Network::Bitcoin)
+            .unwrap();
+        assert_eq!(result.get("mnemonic").unwrap(), "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget divorce twin tonight reason outdoor destroy simple truth cigar social volcano");
+
+        let result = bip85_cmd("wif", 12, None).bip85(Network::Bitcoin).unwrap();
+        assert_eq!(
+            result.get("wif").unwrap(),
+            "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp"
+        );
+
+        let result = bip85_cmd("xprv", 12, None).bip85(Network::Bitcoin).unwrap();
+        assert_eq!(result.get("xprv").unwrap(), "xprv9s21ZrQH143K2srSbCSg4m4kLvPMzcWydgmKEnMmoZUurYuBuYG46c6P71UGXMzmriLzCCBvKQWBUv3vPB3m1SATMhp3uEjXHJ42jFg7myX");
+
+        let result = bip85_cmd("hex", 12, Some(64))
+            .bip85(Network::Bitcoin)
+            .unwrap();
+        assert_eq!(result.get("hex").unwrap(), "492db4698cf3b73a5a24998aa3e9d7fa96275d85724a91e71aa2d645442f878555d078fd1f1f67e368976f04137b1f7a0d19232136ca50c44614af72b5582a5c");
+
+        let result = bip85_cmd("base64", 12, Some(21))
+            .bip85(Network::Bitcoin)
+            .unwrap();
+        assert_eq!(result.get("password").unwrap(), "dKLoepugzdVJvdL56ogNV");
+
+        let result = bip85_cmd("base85", 12, Some(12))
+            .bip85(Network::Bitcoin)
+            .unwrap();
+        assert_eq!(result.get("password").unwrap(), "_s`{TW89)i4`");
+
+        // length out of range
+        assert!(bip85_cmd("hex", 12, Some(8))
+            .bip85(Network::Bitcoin)
+            .is_err());
+        assert!(bip85_cmd("base64", 12, None)
+            .bip85(Network::Bitcoin)
+            .is_err());
+
+        // network mismatch
+        assert!(bip85_cmd("wif", 12, None).bip85(Network::Testnet).is_err());
+    }
+}
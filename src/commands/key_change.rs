@@ -1,144 +1,183 @@
 use crate::cli::Change;
+use crate::commands::Primary;
+use crate::secure::{Xprv, Zeroizing};
+use crate::slip132::{registry, Kind, Version};
 
 use std::str::FromStr;
 
-use bdk::bitcoin::secp256k1::Secp256k1;
-use bdk::bitcoin::base58;
+use crate::error::Error;
 use bdk::bitcoin::bip32::{ExtendedPrivKey, ExtendedPubKey, Fingerprint};
-// use bdk::bitcoin::Network;
-use bdk::keys::KeyError::Message;
-use bdk::Error;
+use bdk::bitcoin::secp256k1::Secp256k1;
 
 use serde_json::json;
 
-fn prefixes(prefix: &str) -> Option<&str> {
-    match prefix {
-        // private bitcoin
-        "xprv" => Some("0488ade4"),
-        "yprv" => Some("049d7878"),
-        "zprv" => Some("04b2430c"),
-        "Yprv" => Some("0295b005"),
-        "Zprv" => Some("02aa7a99"),
-        // private testnet
-        "tprv" => Some("04358394"),
-        "uprv" => Some("044a4e28"),
-        "vprv" => Some("045f18bc"),
-        "Uprv" => Some("024285b5"),
-        "Vprv" => Some("02575048"),
-        // public bitcoin
-        "xpub" => Some("0488b21e"),
-        "ypub" => Some("049d7cb2"),
-        "zpub" => Some("04b24746"),
-        "Ypub" => Some("0295b43f"),
-        "Zpub" => Some("02aa7ed3"),
-        // public testnet
-        "tpub" => Some("043587cf"),
-        "upub" => Some("044a5262"),
-        "vpub" => Some("045f1cf6"),
-        "Upub" => Some("024289ef"),
-        "Vpub" => Some("02575483"),
-        _ => None,
-    }
-}
-
-fn check_format(key: &str, target_format: &str) -> bool {
-    let (prefix, _) = key.split_at(4);
-    let (_, key_type) = prefix.split_at(1);
-    let (_, target_type) = target_format.split_at(1);
+pub fn key_info(key: &str) -> Result<(Fingerprint, Fingerprint, u8), Error> {
+    let registry = registry();
+    let (version, _) = registry.decode(key)?;
+    let standard = Zeroizing::new(registry.to_standard(key)?);
 
-    if key_type == target_type {
-        true
-    } else {
-        false
+    match version.kind {
+        Kind::Private => {
+            let secp = Secp256k1::new();
+            let xprv = Xprv(ExtendedPrivKey::from_str(&standard)?);
+            Ok((xprv.fingerprint(&secp), xprv.parent_fingerprint, xprv.depth))
+        }
+        Kind::Public => {
+            let xpub = ExtendedPubKey::from_str(&standard)?;
+            Ok((xpub.fingerprint(), xpub.parent_fingerprint, xpub.depth))
+        }
     }
 }
 
-fn converter(key: &str, format: &str) -> Option<String> {
-    let prefix_origin = prefixes(format);
+/// Replaces the extended keys found in a text (e.g. a descriptor or an origin-prefixed key)
+/// by the result of `change`, the keys it returns `None` for and everything else are kept as is
+pub fn change_keys<F>(text: &str, mut change: F) -> Result<String, Error>
+where
+    F: FnMut(&str, &Version) -> Result<Option<String>, Error>,
+{
+    let registry = registry();
+    let mut changed = String::with_capacity(text.len());
 
-    if prefix_origin.is_none() {
-        return None;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len())
+            .max(1);
+        let (token, tail) = rest.split_at(end);
+        match registry.decode(token) {
+            Ok((version, _)) => match change(token, version)? {
+                Some(key) => changed.push_str(&key),
+                None => changed.push_str(token),
+            },
+            _ => changed.push_str(token),
+        }
+        rest = tail;
     }
 
-    let prefix_target: Vec<u8> = prefix_origin
-        .unwrap()
-        .chars()
-        .collect::<Vec<char>>()
-        .chunks(2)
-        .map(|dig| {
-            let d: String = dig.into_iter().collect();
-            u8::from_str_radix(&d, 16).unwrap()
-        })
-        .collect();
-
-    let data = base58::decode_check(key).unwrap();
-    let data = [&prefix_target, &data[4..]].concat();
-
-    Some(base58::encode_check(&data))
+    Ok(changed)
 }
 
-pub fn key_info(key: &str) -> Option<(Fingerprint, Fingerprint, u8)> {
-    let (prefix, _) = key.split_at(4);
-    let secp = Secp256k1::new();
+// converts the keys of a line of the target kind
+fn convert_line(line: &str, target: &Version) -> Result<String, Error> {
+    change_keys(line, |key, version| match version.kind == target.kind {
+        true => registry().convert(key, &target.name).map(Some),
+        false => Ok(None),
+    })
+}
 
-    match prefix {
-        "xprv" | "yprv" | "zprv" | "Yprv" | "Zprv" => {
-            let xprv_string = converter(key, "xprv").unwrap();
-            let xprv = ExtendedPrivKey::from_str(&xprv_string).unwrap();
-            Some((xprv.fingerprint(&secp), xprv.parent_fingerprint, xprv.depth))
-        }
-        "tprv" | "uprv" | "vprv" | "Uprv" | "Vprv" => {
-            let tprv_string = converter(key, "tprv").unwrap();
-            let tprv = ExtendedPrivKey::from_str(&tprv_string).unwrap();
-            Some((tprv.fingerprint(&secp), tprv.parent_fingerprint, tprv.depth))
-        }
-        "xpub" | "ypub" | "zpub" | "Ypub" | "Zpub" => {
-            let xpub_string = converter(key, "xpub").unwrap();
-            let xpub = ExtendedPubKey::from_str(&xpub_string).unwrap();
-            Some((xpub.fingerprint(), xpub.parent_fingerprint, xpub.depth))
-        }
-        "tpub" | "upub" | "vpub" | "Upub" | "Vpub" => {
-            let tpub_string = converter(key, "tpub").unwrap();
-            let tpub = ExtendedPubKey::from_str(&tpub_string).unwrap();
-            Some((tpub.fingerprint(), tpub.parent_fingerprint, tpub.depth))
-        }
-        _ => None,
+// one key per line, or keys embedded in descriptors
+fn bulk(keys: &str) -> bool {
+    let mut lines = keys.lines().map(str::trim).filter(|line| !line.is_empty());
+    match (lines.next(), lines.next()) {
+        (Some(line), None) => !line.chars().all(|c| c.is_ascii_alphanumeric()),
+        (None, _) => false,
+        _ => true,
     }
 }
 
 impl Change {
     pub fn change(&self) -> Result<serde_json::Value, Error> {
-        if check_format(&self.key, &self.format) == false {
-            return Err(Error::Key(Message(format!(
-                "Invalid target format to {}",
-                &self.format
-            ))));
+        let registry = registry();
+        let key = self.key.trim();
+
+        if bulk(key) {
+            return self.change_bulk(key);
         }
 
-        let info = key_info(&self.key);
-        if info.is_none() {
-            return Err(Error::Key(Message(format!(
-                "Invalid target format to {}",
-                &self.format
-            ))));
+        let (fingerprint, parent_fingerprint, depth) = key_info(key)?;
+        let mut result = json!({
+            "fingerprint": fingerprint,
+            "parent_fingerprint": parent_fingerprint,
+            "depth": depth,
+        });
+
+        match &self.format {
+            _ if self.list => {
+                let targets = registry.targets(key)?;
+                return Ok(json!({
+                    "targets": targets.iter().map(|v| v.to_json()).collect::<Vec<_>>()
+                }));
+            }
+            _ if self.all => {
+                let mut keys = serde_json::Map::new();
+                for target in registry.targets(key)? {
+                    keys.insert(
+                        target.name.clone(),
+                        json!(registry.convert(key, &target.name)?),
+                    );
+                }
+                result["keys"] = serde_json::Value::Object(keys);
+            }
+            Some(format) => result[format] = json!(registry.convert(key, format)?),
+            None => return Err(Error::Input("A target format is required".to_string())),
         }
 
-        let key_target = converter(&self.key, &self.format);
-        if key_target.is_none() {
-            return Err(Error::Key(Message(format!(
-                "Invalid target format to {}",
-                &self.format
-            ))));
+        Ok(result)
+    }
+
+    fn change_bulk(&self, keys: &str) -> Result<serde_json::Value, Error> {
+        let registry = registry();
+        if self.list {
+            return Err(Error::Input(
+                "Only a single key can list its formats".to_string(),
+            ));
         }
 
-        let (fingerprint, parent_fingerprint, depth) = info.unwrap();
+        let mut converted = vec![];
+        for (i, line) in keys.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let at_line = |e: Error| match e {
+                Error::Network(message) => Error::Network(format!("Line {}: {}", i + 1, message)),
+                Error::Input(message) => Error::Input(format!("Line {}: {}", i + 1, message)),
+                e => e,
+            };
 
-        Ok(json!({
-            "fingerprint": fingerprint,
-            "parent_fingerprint": parent_fingerprint,
-            "depth": depth,
-            &self.format: key_target.unwrap()
-        }))
+            let targets = match &self.format {
+                _ if self.all => {
+                    // the encodings of the network and kind of the first key of the line
+                    let first = line
+                        .split(|c: char| !c.is_ascii_alphanumeric())
+                        .find(|token| registry.decode(token).is_ok())
+                        .ok_or_else(|| {
+                            Error::Parse(format!("Line {}: no extended key found", i + 1))
+                        })?;
+                    registry.targets(first)?
+                }
+                Some(format) => vec![registry
+                    .get(format)
+                    .ok_or_else(|| Error::Input(format!("Unknown key format {}", format)))?],
+                None => return Err(Error::Input("A target format is required".to_string())),
+            };
+
+            let mut line_keys = serde_json::Map::new();
+            for target in targets {
+                line_keys.insert(
+                    target.name.clone(),
+                    json!(convert_line(line, target).map_err(at_line)?),
+                );
+            }
+            converted.push(match &self.format {
+                Some(format) if !self.all => line_keys.remove(format).unwrap_or_default(),
+                _ => serde_json::Value::Object(line_keys),
+            });
+        }
+
+        Ok(json!({ "keys": converted }))
+    }
+}
+
+impl Primary for Change {
+    fn primary(&self) -> &str {
+        match &self.format {
+            _ if self.list => "targets.name",
+            _ if self.all || bulk(&self.key) => "keys",
+            Some(format) => format,
+            None => "keys",
+        }
     }
 }
 
@@ -149,7 +188,9 @@ mod test {
     #[test]
     fn test_key_change() {
         let key_change_cmd = Change{
-            format: String::from("zpub"),
+            format: Some(String::from("zpub")),
+            list: false,
+            all: false,
             key: String::from("xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V"),
         };
         let result = key_change_cmd.change().unwrap();
@@ -162,11 +203,58 @@ mod test {
         assert_eq!(depth, 3);
 
         let key_change_cmd = Change{
-            format: String::from("zprv"),
+            format: Some(String::from("zprv")),
+            list: false,
+            all: false,
             key: String::from("xpub6C1SYcQFFDMvLQxLHU6wUHGY8EP6dBtjckDGZpj4hbxw4aVuKKNAmkFbpPwzQLibWmXJmamZHKsboCdZzHqTsbeU5vi169HHHNZGdNjG2qa"),
         };
         let result = key_change_cmd.change();
 
         assert!(result.is_err());
+
+        // malformed keys are errors rather than panics
+        for key in ["", "xp", "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuy"] {
+            let key_change_cmd = Change {
+                format: Some(String::from("zpub")),
+                list: false,
+                all: false,
+                key: String::from(key),
+            };
+            assert!(key_change_cmd.change().is_err());
+        }
+
+        let key_change_cmd = Change {
+            format: None,
+            list: true,
+            all: false,
+            key: String::from("xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V"),
+        };
+        let result = key_change_cmd.change().unwrap();
+        let targets = result["targets"].as_array().unwrap();
+        assert_eq!(targets.len(), 5);
+        assert_eq!(targets[2]["name"], "zpub");
+        assert_eq!(targets[2]["script_type"], "p2wpkh");
+
+        // bulk, origins and descriptors are kept around the keys
+        let key_change_cmd = Change {
+            format: Some(String::from("xpub")),
+            list: false,
+            all: false,
+            key: String::from("[73c5da0a/84'/0'/0']zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs\n\
+                wpkh([73c5da0a/84'/0'/0']zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs/0/*)\n"),
+        };
+        let result = key_change_cmd.change().unwrap();
+        assert_eq!(result["keys"][0], "[73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V");
+        assert_eq!(result["keys"][1], "wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)");
+
+        let key_change_cmd = Change {
+            format: None,
+            list: false,
+            all: true,
+            key: String::from("xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V"),
+        };
+        let result = key_change_cmd.change().unwrap();
+        assert_eq!(result["keys"].as_object().unwrap().len(), 5);
+        assert_eq!(result["keys"]["zpub"], "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs");
     }
 }
@@ -0,0 +1,95 @@
+use crate::cli::Combine;
+use crate::commands::Primary;
+use crate::secure::Xprv;
+use crate::slip39;
+use crate::util::{passphrase, prompt, shares};
+
+use crate::error::Error;
+use bdk::bitcoin::bip32::ExtendedPrivKey;
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::Network;
+
+use serde_json::json;
+
+impl Combine {
+    pub fn combine(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let secp = Secp256k1::new();
+
+        let mut shares = shares(&self.shares);
+        if shares.is_empty() {
+            // one share per prompt, until an empty one
+            loop {
+                let share = prompt(&format!("share {}", shares.len() + 1), false)?;
+                if share.trim().is_empty() {
+                    break;
+                }
+                shares.push(share.trim().to_string());
+            }
+        }
+
+        let passphrase = passphrase(&self.passphrase, "passphrase")?;
+        let seed = slip39::combine(&shares, passphrase.as_bytes())?;
+
+        let xprv = Xprv(ExtendedPrivKey::new_master(network, &seed)?);
+        let fingerprint = xprv.fingerprint(&secp);
+
+        Ok(json!({ "fingerprint": fingerprint.to_string(), "xprv": xprv.to_string() }))
+    }
+}
+
+impl Primary for Combine {
+    fn primary(&self) -> &str {
+        "xprv"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::cli::{Restore, Split};
+
+    #[test]
+    fn test_combine() {
+        let mnemonic = String::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about");
+
+        let key_split_cmd = Split {
+            mnemonic: Some(mnemonic.clone()),
+            password: Some(Some(String::from("demo"))),
+            secret: None,
+            groups: vec![(2, 3), (2, 3)],
+            group_threshold: 1,
+            passphrase: Some(Some(String::from("slip39"))),
+            exponent: 0,
+        };
+        let split = key_split_cmd.split(Network::Testnet).unwrap();
+        let shares = &split["groups"][1]["shares"];
+
+        // same master key than restoring the mnemonic
+        let key_combine_cmd = Combine {
+            shares: vec![
+                shares[2].as_str().unwrap().to_string(),
+                shares[0].as_str().unwrap().to_string(),
+            ],
+            passphrase: Some(Some(String::from("slip39"))),
+        };
+        let result = key_combine_cmd.combine(Network::Testnet).unwrap();
+
+        let key_restore_cmd = Restore {
+            mnemonic: Some(mnemonic),
+            codex32: vec![],
+            language: None,
+            password: Some(Some(String::from("demo"))),
+            account: None,
+        };
+        assert_eq!(result, key_restore_cmd.restore(Network::Testnet).unwrap());
+        assert_eq!(result.get("fingerprint").unwrap(), "03393bdd");
+
+        // one share of a 2of3 group is not enough
+        let key_combine_cmd = Combine {
+            shares: vec![shares[1].as_str().unwrap().to_string()],
+            passphrase: Some(Some(String::from("slip39"))),
+        };
+        assert!(key_combine_cmd.combine(Network::Testnet).is_err());
+    }
+}
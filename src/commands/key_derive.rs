@@ -1,46 +1,124 @@
+use crate::account::{checksummed, origin_key};
 use crate::cli::Derive;
+use crate::commands::Primary;
+use crate::secure::Xprv;
+use crate::slip132::registry;
+use crate::util::{prompt, xpub};
 
+use crate::error::Error;
+use bdk::bitcoin::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
 use bdk::bitcoin::secp256k1::Secp256k1;
-use bdk::bitcoin::bip32::{DerivationPath, KeySource};
 use bdk::bitcoin::Network;
-use bdk::descriptor::Segwitv0;
-use bdk::keys::KeyError::{InvalidNetwork, Message};
-use bdk::keys::{DerivableKey, DescriptorKey};
-use bdk::Error;
 
 use serde_json::json;
+use std::str::FromStr;
 
 impl Derive {
     pub fn derive(&self, network: Network) -> Result<serde_json::Value, Error> {
-        if self.xprv.network != network {
-            return Err(Error::Key(InvalidNetwork));
+        if let Some(key) = &self.xpub {
+            return self.derive_public(network, key);
         }
 
+        let xprv = match self.xprv {
+            Some(xprv) => Xprv(xprv),
+            None => Xprv(ExtendedPrivKey::from_str(&prompt("xprv", false)?)?),
+        };
+        if xprv.network != network {
+            return Err(Error::network_mismatch(xprv.network, network));
+        }
+
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => self.script_type.account_path(network, self.account)?,
+        };
+
         let secp = Secp256k1::new();
-        let derived_xprv = &self.xprv.derive_priv(&secp, &self.path)?;
-        let origin: KeySource = (self.xprv.fingerprint(&secp), self.path.clone());
+        let derived_xprv = Xprv(xprv.derive_priv(&secp, &path)?);
+        let xpub = ExtendedPubKey::from_priv(&secp, &derived_xprv);
+        let fingerprint = xprv.fingerprint(&secp).to_string();
 
-        let derived_xprv_desc_key: DescriptorKey<Segwitv0> =
-            derived_xprv.into_descriptor_key(Some(origin), DerivationPath::default())?;
+        let mut result = self.output(network, &fingerprint, &path, &xpub)?;
+        result["xprv"] = json!(format!(
+            "{}/*",
+            origin_key(&fingerprint, &path, &derived_xprv.to_string())
+        ));
 
-        if let DescriptorKey::Secret(desc_seckey, _, _) = derived_xprv_desc_key {
-            let desc_pubkey = desc_seckey
-                .to_public(&secp)
-                .map_err(|e| Error::Generic(e.to_string()))?;
+        Ok(result)
+    }
 
-            Ok(json!({ "xprv": desc_seckey.to_string(), "xpub": desc_pubkey.to_string() }))
-        } else {
-            Err(Error::Key(Message("Invalid key variant".to_string())))
+    // non-hardened derivation only, the origin of the input key is extended when known
+    fn derive_public(&self, network: Network, key: &str) -> Result<serde_json::Value, Error> {
+        let (origin, xpub) = xpub(key)?;
+        // the derived key keeps the version of the input key, e.g. a zpub
+        let format = match &self.format {
+            Some(format) => format.clone(),
+            None => {
+                let encoded = key.rsplit(']').next().unwrap_or(key);
+                registry().decode(encoded)?.0.name.clone()
+            }
+        };
+        if xpub.network != network {
+            return Err(Error::network_mismatch(xpub.network, network));
         }
+
+        let path = self.path.clone().unwrap_or_default();
+        if let Some(step) = path.into_iter().find(|step| step.is_hardened()) {
+            return Err(Error::Input(format!(
+                "Cannot derive the hardened step {} from a public key",
+                step
+            )));
+        }
+
+        let secp = Secp256k1::new();
+        let derived = xpub.derive_pub(&secp, &path)?;
+        let (fingerprint, path) = match origin {
+            Some((fingerprint, origin_path)) => (fingerprint, origin_path.extend(&path)),
+            None => (xpub.fingerprint(), path),
+        };
+
+        let mut result = self.output(network, &fingerprint.to_string(), &path, &derived)?;
+        let converted = registry().convert(&derived.to_string(), &format)?;
+        result["xpub"] = json!(format!(
+            "{}/*",
+            origin_key(&fingerprint.to_string(), &path, &converted)
+        ));
+
+        Ok(result)
+    }
+
+    fn output(
+        &self,
+        network: Network,
+        fingerprint: &str,
+        path: &DerivationPath,
+        xpub: &ExtendedPubKey,
+    ) -> Result<serde_json::Value, Error> {
+        let xpub = xpub.to_string();
+        let key = origin_key(fingerprint, path, &xpub);
+        let descriptor =
+            |chain: u32| checksummed(&self.script_type.descriptor(&format!("{}/{}/*", key, chain)));
+
+        Ok(json!({
+            "xpub": format!("{}/*", key),
+            "script_type": self.script_type.name(),
+            "receive": descriptor(0)?,
+            "change": descriptor(1)?,
+            "slip132": registry().convert(&xpub, self.script_type.slip132(network))?,
+        }))
+    }
+}
+
+impl Primary for Derive {
+    fn primary(&self) -> &str {
+        "xpub"
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::str::FromStr;
 
-    use bdk::bitcoin::bip32::ExtendedPrivKey;
+    use crate::account::ScriptType;
 
     // https://github.com/satoshilabs/slips/blob/master/slip-0132.md
 
@@ -48,8 +126,12 @@ mod test {
     fn test_key_derive() {
         // m/44'/0'/0'
         let key_derive_cmd = Derive{
-            path: DerivationPath::from_str("m/44'/0'/0'").unwrap(),
-            xprv: ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap()
+            path: Some(DerivationPath::from_str("m/44'/0'/0'").unwrap()),
+            script_type: ScriptType::Segwit,
+            account: 0,
+            xprv: Some(ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap()),
+            xpub: None,
+            format: None,
         };
         let result = key_derive_cmd.derive(Network::Bitcoin).unwrap();
         let xprv = result.get("xprv").unwrap().as_str().unwrap();
@@ -60,8 +142,12 @@ mod test {
 
         // m/49'/0'/0'
         let key_derive_cmd = Derive{
-            path: DerivationPath::from_str("m/49'/0'/0'").unwrap(),
-            xprv: ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap()
+            path: Some(DerivationPath::from_str("m/49'/0'/0'").unwrap()),
+            script_type: ScriptType::Segwit,
+            account: 0,
+            xprv: Some(ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap()),
+            xpub: None,
+            format: None,
         };
         let result = key_derive_cmd.derive(Network::Bitcoin).unwrap();
         let xprv = result.get("xprv").unwrap().as_str().unwrap();
@@ -72,8 +158,12 @@ mod test {
 
         // m/84'/0'/0'
         let key_derive_cmd = Derive{
-            path: DerivationPath::from_str("m/84'/0'/0'").unwrap(),
-            xprv: ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap()
+            path: Some(DerivationPath::from_str("m/84'/0'/0'").unwrap()),
+            script_type: ScriptType::Segwit,
+            account: 0,
+            xprv: Some(ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap()),
+            xpub: None,
+            format: None,
         };
         let result = key_derive_cmd.derive(Network::Bitcoin).unwrap();
         let xprv = result.get("xprv").unwrap().as_str().unwrap();
@@ -81,5 +171,76 @@ mod test {
 
         assert_eq!(xprv, "[73c5da0a/84'/0'/0']xprv9ybY78BftS5UGANki6oSifuQEjkpyAC8ZmBvBNTshQnCBcxnefjHS7buPMkkqhcRzmoGZ5bokx7GuyDAiktd5HemohAU4wV1ZPMDRmLpBMm/*");
         assert_eq!(xpub, "[73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/*");
+        assert_eq!(result.get("slip132").unwrap(), "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs");
+        assert!(result.get("receive").unwrap().as_str().unwrap().starts_with("wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)#"));
+
+        // default path of the script type, coin type 1 on testnet
+        let key_derive_cmd = Derive{
+            path: None,
+            script_type: ScriptType::Taproot,
+            account: 1,
+            xprv: Some(ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPeE6XnhCjJ5WHgod5tWXv3W3jujmxAGsgjTr8ewZan8YvomaGTDmQyYkUJuGx4XUq5czx7nytjAGSHEv1XgYqj41X3NCT3xU").unwrap()),
+            xpub: None,
+            format: None,
+        };
+        let result = key_derive_cmd.derive(Network::Testnet).unwrap();
+        let change = result.get("change").unwrap().as_str().unwrap();
+
+        assert!(change.starts_with("tr([03393bdd/86'/1'/1']tpub"));
+        assert!(change.contains("/1/*)#"));
+        assert!(result
+            .get("slip132")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .starts_with("tpub"));
+
+        // public derivation from a SLIP-132 account key, the origin is chained
+        // https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki#test-vectors
+        let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+        let key_derive_cmd = Derive {
+            path: Some(DerivationPath::from_str("m/0/0").unwrap()),
+            script_type: ScriptType::Segwit,
+            account: 0,
+            xprv: None,
+            xpub: Some(format!("[73c5da0a/84'/0'/0']{}", zpub)),
+            format: None,
+        };
+        let result = key_derive_cmd.derive(Network::Bitcoin).unwrap();
+        let xpub = result.get("xpub").unwrap().as_str().unwrap();
+
+        assert!(xpub.starts_with("[73c5da0a/84'/0'/0'/0/0]zpub"));
+        let standard = registry()
+            .to_standard(&xpub[xpub.find(']').unwrap() + 1..xpub.len() - 2])
+            .unwrap();
+        let key = ExtendedPubKey::from_str(&standard).unwrap();
+        assert_eq!(
+            key.public_key.to_string(),
+            "0330d54fd0dd420a6e5f8d3624f5f3482cae350f79d5f0753bf5beef9c2d91af3c"
+        );
+
+        // the version asked for
+        let key_derive_cmd = Derive {
+            path: Some(DerivationPath::from_str("m/0/0").unwrap()),
+            script_type: ScriptType::Segwit,
+            account: 0,
+            xprv: None,
+            xpub: Some(String::from(zpub)),
+            format: Some(String::from("xpub")),
+        };
+        let result = key_derive_cmd.derive(Network::Bitcoin).unwrap();
+        assert_eq!(result["xpub"], format!("[fd13aac9/0/0]{}/*", standard));
+
+        // a hardened step needs the private key
+        let key_derive_cmd = Derive {
+            path: Some(DerivationPath::from_str("m/0'").unwrap()),
+            script_type: ScriptType::Segwit,
+            account: 0,
+            xprv: None,
+            xpub: Some(String::from(zpub)),
+            format: None,
+        };
+        let err = key_derive_cmd.derive(Network::Bitcoin).unwrap_err();
+        assert!(err.to_string().contains("hardened"));
     }
 }
@@ -1,12 +1,18 @@
+use crate::account::accounts;
 use crate::cli::Generate;
+use crate::codex32;
+use crate::commands::Primary;
+use crate::secure::{Xprv, Zeroizing};
+use crate::util::passphrase;
 
+use crate::error::Error;
+use bdk::bitcoin::bip32::ExtendedPrivKey;
 use bdk::bitcoin::hashes::{sha256, Hash};
 use bdk::bitcoin::secp256k1::Secp256k1;
 use bdk::bitcoin::Network;
-use bdk::keys::bip39::{Language, Mnemonic, WordCount};
-use bdk::keys::{DerivableKey, ExtendedKey, GeneratableKey, GeneratedKey};
+use bdk::keys::bip39::{Mnemonic, WordCount};
+use bdk::keys::{GeneratableKey, GeneratedKey};
 use bdk::miniscript::miniscript;
-use bdk::Error;
 
 use rand::thread_rng;
 use rand::Rng;
@@ -15,57 +21,296 @@ use serde_json::json;
 
 impl Generate {
     pub fn generate(&self, network: Network) -> Result<serde_json::Value, Error> {
-        let secp = Secp256k1::new();
-
         let mnemonic_type = match self.entropy {
             12 => WordCount::Words12,
+            15 => WordCount::Words15,
+            18 => WordCount::Words18,
+            21 => WordCount::Words21,
             _ => WordCount::Words24,
         };
 
-        // radom entropy for generate the final mnemonic phrase
-        let entropy_str = (0..self.dicerolls).fold("".to_owned(), |acc, _| {
-            let num: u16 = thread_rng().gen_range(0..65535);
-            acc + &num.to_string()
-        });
+        let entropy_str = match &self.rolls {
+            // user supplied dice rolls, the same rolls always give the same mnemonic
+            Some(rolls) => dice_rolls(rolls, self.entropy as usize * 32 / 3)?,
+            // radom entropy for generate the final mnemonic phrase
+            None => {
+                // allocated once so no unwiped copy is left behind by a reallocation
+                let mut acc = Zeroizing::new(String::with_capacity(self.dicerolls * 5));
+                for _ in 0..self.dicerolls {
+                    let num: u16 = thread_rng().gen_range(0..65535);
+                    acc.push_str(&num.to_string());
+                }
+                acc
+            }
+        };
 
-        let entropy_hash = sha256::Hash::hash(entropy_str.as_bytes());
+        let entropy_hash =
+            Zeroizing::new(sha256::Hash::hash(entropy_str.as_bytes()).to_byte_array());
 
-        let mnemonic: GeneratedKey<_, miniscript::BareCtx> = Mnemonic::generate_with_entropy(
-            (mnemonic_type, Language::English),
-            *entropy_hash.as_ref(),
-        )
-        .map_err(|_| Error::Generic("Mnemonic generation error".to_string()))?;
+        let mut result = match self.codex32 {
+            true => self.generate_codex32(network, &entropy_hash)?,
+            false => self.generate_mnemonic(network, mnemonic_type, &entropy_hash)?,
+        };
+        if self.rolls.is_some() {
+            result["dicerolls"] = json!(entropy_str.len());
+            result["entropy_bits"] = json!(dice_entropy_bits(entropy_str.len()));
+        }
+
+        Ok(result)
+    }
+
+    fn generate_mnemonic(
+        &self,
+        network: Network,
+        mnemonic_type: WordCount,
+        entropy_hash: &[u8; 32],
+    ) -> Result<serde_json::Value, Error> {
+        let secp = Secp256k1::new();
+
+        let mnemonic: GeneratedKey<_, miniscript::BareCtx> =
+            Mnemonic::generate_with_entropy((mnemonic_type, self.language), *entropy_hash)
+                .map_err(|_| Error::Input("Mnemonic generation error".to_string()))?;
 
         let mnemonic = mnemonic.into_key();
-        let xkey: ExtendedKey = (mnemonic.clone(), self.password.clone()).into_extended_key()?;
+        let password = passphrase(&self.password, "password")?;
+        let seed = Zeroizing::new(mnemonic.to_seed(password.as_str()));
+        let xprv = Xprv(ExtendedPrivKey::new_master(network, &*seed)?);
+
+        let fingerprint = xprv.fingerprint(&secp);
+        let phrase = mnemonic.to_string();
+
+        let mut result = json!({ "fingerprint": fingerprint.to_string(), "mnemonic": phrase, "xprv": xprv.to_string() });
+        if let Some(account) = self.account {
+            result["accounts"] = accounts(&xprv, account)?;
+        }
 
-        let xprv = xkey.into_xprv(network).ok_or_else(|| {
-            Error::Generic("Privatekey info not found (should not happen)".to_string())
-        })?;
+        Ok(result)
+    }
+
+    // the master seed is the entropy itself, codex32 has no mnemonic derivation step
+    fn generate_codex32(
+        &self,
+        network: Network,
+        entropy_hash: &[u8; 32],
+    ) -> Result<serde_json::Value, Error> {
+        let secp = Secp256k1::new();
+
+        let seed = &entropy_hash[..self.entropy as usize * 4 / 3];
+        let shares = codex32::generate(
+            seed,
+            self.threshold,
+            self.shares.unwrap_or(self.threshold),
+            self.identifier.as_deref(),
+        )?;
 
+        let xprv = Xprv(ExtendedPrivKey::new_master(network, seed)?);
         let fingerprint = xprv.fingerprint(&secp);
-        let phrase = mnemonic
-            .word_iter()
-            .fold("".to_string(), |phrase, w| phrase + w + " ")
-            .trim()
-            .to_string();
-
-        Ok(
-            json!({ "fingerprint": fingerprint.to_string(), "mnemonic": phrase, "xprv": xprv.to_string(), /*"xpub": xpub.to_string()*/ }),
-        )
+        let shares: Vec<String> = shares.iter().map(|share| share.to_string()).collect();
+
+        let mut result = json!({
+            "fingerprint": fingerprint.to_string(),
+            "identifier": shares[0][4..8],
+            "threshold": self.threshold,
+            "shares": shares,
+            "xprv": xprv.to_string()
+        });
+        if let Some(account) = self.account {
+            result["accounts"] = accounts(&xprv, account)?;
+        }
+
+        Ok(result)
+    }
+}
+
+// Whole bits of entropy collected from a number of d6 rolls (log2(6) bits per roll)
+fn dice_entropy_bits(rolls: usize) -> usize {
+    (rolls as f64 * 6f64.log2()).floor() as usize
+}
+
+// Rolls carrying at least the required bits, e.g. 50 for 128 bits and 100 for 256 bits
+fn dice_rolls_required(bits: usize) -> usize {
+    (bits as f64 / 6f64.log2()).ceil() as usize
+}
+
+// Validates physical d6 dice rolls and checks they carry the required bits of entropy
+fn dice_rolls(rolls: &str, required: usize) -> Result<Zeroizing<String>, Error> {
+    let rolls: Zeroizing<String> =
+        Zeroizing::new(rolls.chars().filter(|c| !c.is_whitespace()).collect());
+
+    if let Some(c) = rolls.chars().find(|c| !('1'..='6').contains(c)) {
+        return Err(Error::Parse(format!(
+            "Invalid dice roll '{}', expected digits 1-6",
+            c
+        )));
+    }
+
+    if rolls.len() < dice_rolls_required(required) {
+        return Err(Error::Input(format!(
+            "Not enough dice rolls: {} rolls give {} bits of entropy, {} rolls ({} bits) are required",
+            rolls.len(),
+            dice_entropy_bits(rolls.len()),
+            dice_rolls_required(required),
+            required
+        )));
     }
+
+    Ok(rolls)
 }
 
-// #[cfg(test)]
-// mod test {
-//     use super::*;
+impl Primary for Generate {
+    fn primary(&self) -> &str {
+        match self.codex32 {
+            true => "shares",
+            false => "mnemonic",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bdk::keys::bip39::Language;
+
+    #[test]
+    fn test_generate() {
+        let rolls = "123456".repeat(16) + "1234";
+
+        // the mnemonic encodes the first bytes of sha256(rolls)
+        let key_generate_cmd = Generate {
+            dicerolls: 99,
+            rolls: Some(rolls.clone()),
+            entropy: 24,
+            language: Language::English,
+            password: None,
+            codex32: false,
+            threshold: 0,
+            shares: None,
+            identifier: None,
+            account: None,
+        };
+        let result = key_generate_cmd.generate(Network::Bitcoin).unwrap();
+        let phrase = result.get("mnemonic").unwrap().as_str().unwrap();
+        let mnemonic = Mnemonic::parse_in(Language::English, phrase).unwrap();
+
+        assert_eq!(
+            mnemonic.to_entropy(),
+            sha256::Hash::hash(rolls.as_bytes()).to_byte_array()
+        );
+        assert_eq!(result.get("dicerolls").unwrap(), 100);
+        assert_eq!(result.get("entropy_bits").unwrap(), 258);
 
-//     #[test]
-//     fn test_generate() {
+        // same rolls, same result
+        let key_generate_cmd = Generate {
+            dicerolls: 99,
+            rolls: Some(rolls.clone()),
+            entropy: 24,
+            language: Language::English,
+            password: None,
+            codex32: false,
+            threshold: 0,
+            shares: None,
+            identifier: None,
+            account: None,
+        };
+        assert_eq!(key_generate_cmd.generate(Network::Bitcoin).unwrap(), result);
+
+        // 50 rolls are enough for 12 words, 99 rolls (255.9 bits) are not enough for 24
+        let key_generate_cmd = Generate {
+            dicerolls: 99,
+            rolls: Some(rolls[..50].to_string()),
+            entropy: 12,
+            language: Language::English,
+            password: None,
+            codex32: false,
+            threshold: 0,
+            shares: None,
+            identifier: None,
+            account: None,
+        };
+        assert!(key_generate_cmd.generate(Network::Bitcoin).is_ok());
+
+        let key_generate_cmd = Generate {
+            dicerolls: 99,
+            rolls: Some(rolls[..99].to_string()),
+            entropy: 24,
+            language: Language::English,
+            password: None,
+            codex32: false,
+            threshold: 0,
+            shares: None,
+            identifier: None,
+            account: None,
+        };
+        assert!(key_generate_cmd.generate(Network::Bitcoin).is_err());
+        assert_eq!(dice_rolls_required(256), 100);
+
+        // only d6 faces are valid
+        let key_generate_cmd = Generate {
+            dicerolls: 99,
+            rolls: Some(rolls.replace('6', "7")),
+            entropy: 24,
+            language: Language::English,
+            password: None,
+            codex32: false,
+            threshold: 0,
+            shares: None,
+            identifier: None,
+            account: None,
+        };
+        assert!(key_generate_cmd.generate(Network::Bitcoin).is_err());
 
-//         assert_eq!(
-//             generate(),
-//             Ok(json!({ "fingerprint": fingerprint.to_string(), "mnemonic": phrase, "xprv": xprv.to_string() }))
-//         );
-//     }
-// }
+        // every word count in every language
+        for (entropy, words) in [(15, 15), (18, 18), (21, 21)] {
+            let key_generate_cmd = Generate {
+                dicerolls: 99,
+                rolls: None,
+                entropy,
+                language: Language::Japanese,
+                password: None,
+                codex32: false,
+                threshold: 0,
+                shares: None,
+                identifier: None,
+                account: None,
+            };
+            let result = key_generate_cmd.generate(Network::Bitcoin).unwrap();
+            let phrase = result.get("mnemonic").unwrap().as_str().unwrap();
+            let mnemonic = Mnemonic::parse_in(Language::Japanese, phrase).unwrap();
+
+            assert_eq!(mnemonic.word_count(), words);
+        }
+
+        // codex32 shares of the rolls
+        let key_generate_cmd = Generate {
+            dicerolls: 99,
+            rolls: Some(rolls.clone()),
+            entropy: 12,
+            language: Language::English,
+            password: None,
+            codex32: true,
+            threshold: 2,
+            shares: Some(3),
+            identifier: Some(String::from("test")),
+            account: None,
+        };
+        let result = key_generate_cmd.generate(Network::Bitcoin).unwrap();
+        let shares: Vec<String> = result["shares"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s.as_str().unwrap().to_string())
+            .collect();
+        let seed = codex32::recover(&shares[1..]).unwrap();
+
+        assert_eq!(shares.len(), 3);
+        assert_eq!(result.get("identifier").unwrap(), "test");
+        assert_eq!(*seed, sha256::Hash::hash(rolls.as_bytes())[..16]);
+        assert_eq!(
+            result.get("xprv").unwrap(),
+            &ExtendedPrivKey::new_master(Network::Bitcoin, &seed)
+                .unwrap()
+                .to_string()
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use std::str::FromStr;
+
+use crate::cli::Inspect;
+use crate::commands::Primary;
+use crate::secure::{Xprv, Zeroizing};
+use crate::slip132::{registry, Kind};
+use crate::util::{descriptor_script, required};
+
+use crate::error::Error;
+use bdk::bitcoin::address::NetworkUnchecked;
+use bdk::bitcoin::base58;
+use bdk::bitcoin::bip32::{ExtendedPrivKey, ExtendedPubKey, KeySource};
+use bdk::bitcoin::key::XOnlyPublicKey;
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::{Address, Network, PrivateKey, PublicKey};
+use bdk::descriptor::{Descriptor, DescriptorPublicKey};
+use bdk::miniscript::descriptor::checksum::desc_checksum;
+use bdk::miniscript::ForEachKey;
+
+use serde_json::json;
+
+const NETWORKS: [Network; 4] = [
+    Network::Bitcoin,
+    Network::Testnet,
+    Network::Signet,
+    Network::Regtest,
+];
+
+fn key_origin(key: &DescriptorPublicKey) -> Option<&KeySource> {
+    match key {
+        DescriptorPublicKey::Single(single) => single.origin.as_ref(),
+        DescriptorPublicKey::XPub(xpub) => xpub.origin.as_ref(),
+        DescriptorPublicKey::MultiXPub(xpub) => xpub.origin.as_ref(),
+    }
+}
+
+fn inspect_descriptor(descriptor: &str) -> Result<serde_json::Value, Error> {
+    let (body, checksum) = match descriptor.split_once('#') {
+        Some((body, checksum)) => (body, Some(checksum)),
+        None => (descriptor, None),
+    };
+
+    // the checksum is reported rather than enforced
+    let expected = desc_checksum(body)?;
+    let secp = Secp256k1::new();
+    let (descriptor, keymap) = Descriptor::parse_descriptor(&secp, body)?;
+
+    let mut keys = vec![];
+    descriptor.for_each_key(|key| {
+        let origin = key_origin(key);
+        keys.push(json!({
+            "key": key.to_string(),
+            "fingerprint": key.master_fingerprint().to_string(),
+            "origin_path": origin.map(|(_, path)| path.to_string()),
+        }));
+        true
+    });
+
+    Ok(json!({
+        "kind": "descriptor",
+        "descriptor": descriptor.to_string(),
+        "script_type": descriptor_script(descriptor.desc_type()),
+        "checksum": expected,
+        "checksum_valid": checksum.map(|checksum| checksum == expected),
+        "private": !keymap.is_empty(),
+        "wildcard": descriptor.has_wildcard(),
+        "keys": keys,
+    }))
+}
+
+fn inspect_extended(key: &str) -> Result<serde_json::Value, Error> {
+    let registry = registry();
+    let (version, _) = registry.decode(key)?;
+    let standard = Zeroizing::new(registry.to_standard(key)?);
+
+    let (xpub, xprv) = match version.kind {
+        Kind::Private => {
+            let xprv = Xprv(ExtendedPrivKey::from_str(&standard)?);
+            (
+                ExtendedPubKey::from_priv(&Secp256k1::new(), &xprv),
+                Some(xprv),
+            )
+        }
+        Kind::Public => (ExtendedPubKey::from_str(&standard)?, None),
+    };
+
+    let mut result = json!({
+        "kind": match xprv {
+            Some(_) => "extended_private_key",
+            None => "extended_public_key",
+        },
+        "network": version.network.to_string(),
+        "version": version.hex(),
+        "slip132": version.name,
+        "script_type": version.script_type,
+        "multisig": version.multisig,
+        "depth": xpub.depth,
+        "child_number": xpub.child_number.to_string(),
+        "chain_code": xpub.chain_code.to_string(),
+        "fingerprint": xpub.fingerprint().to_string(),
+        "parent_fingerprint": xpub.parent_fingerprint.to_string(),
+        "public_key": xpub.public_key.to_string(),
+    });
+    if let (Some(_), Some(public)) = (&xprv, registry.counterpart(version)) {
+        result["xpub"] = json!(registry.convert(&xpub.to_string(), &public.name)?);
+    }
+
+    Ok(result)
+}
+
+fn inspect_wif(wif: &str) -> Result<serde_json::Value, Error> {
+    let key = PrivateKey::from_wif(wif).map_err(|e| Error::Parse(e.to_string()))?;
+    let public_key = key.public_key(&Secp256k1::new());
+
+    Ok(json!({
+        "kind": "wif",
+        "network": key.network.to_string(),
+        "compressed": key.compressed,
+        "public_key": public_key.to_string(),
+        "hash160": public_key.pubkey_hash().to_string(),
+    }))
+}
+
+fn inspect_address(address: Address<NetworkUnchecked>) -> serde_json::Value {
+    let networks: Vec<String> = NETWORKS
+        .into_iter()
+        .filter(|network| address.is_valid_for_network(*network))
+        .map(|network| network.to_string())
+        .collect();
+    let address = address.assume_checked();
+
+    json!({
+        "kind": "address",
+        "networks": networks,
+        "script_type": address.address_type().map(|t| t.to_string()),
+        "script_pubkey": address.script_pubkey().to_hex_string(),
+    })
+}
+
+impl Inspect {
+    pub fn inspect(&self) -> Result<serde_json::Value, Error> {
+        let key = required(&self.key, "key")?;
+        let key = key.trim();
+
+        if key.contains('(') {
+            return inspect_descriptor(key);
+        }
+
+        if let Ok(data) = base58::decode_check(key).map(Zeroizing::new) {
+            match (data.len(), data.first()) {
+                (78, _) => return inspect_extended(key),
+                (33 | 34, Some(0x80 | 0xef)) => return inspect_wif(key),
+                _ => (),
+            }
+        }
+
+        if let Ok(public_key) = PublicKey::from_str(key) {
+            return Ok(json!({
+                "kind": "public_key",
+                "compressed": public_key.compressed,
+                "public_key": public_key.to_string(),
+                "hash160": public_key.pubkey_hash().to_string(),
+            }));
+        }
+
+        if let Ok(x_only) = XOnlyPublicKey::from_str(key) {
+            return Ok(json!({
+                "kind": "x_only_public_key",
+                "script_type": "p2tr",
+                "public_key": x_only.to_string(),
+            }));
+        }
+
+        if let Ok(address) = Address::from_str(key) {
+            return Ok(inspect_address(address));
+        }
+
+        Err(Error::Parse(
+            "Unrecognized key, descriptor or address".to_string(),
+        ))
+    }
+}
+
+impl Primary for Inspect {
+    fn primary(&self) -> &str {
+        "public_key"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn inspect(key: &str) -> serde_json::Value {
+        Inspect {
+            key: Some(String::from(key)),
+        }
+        .inspect()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_inspect() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki#test-vectors
+        let result = inspect("zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs");
+        assert_eq!(result["kind"], "extended_public_key");
+        assert_eq!(result["network"], "bitcoin");
+        assert_eq!(result["version"], "04b24746");
+        assert_eq!(result["script_type"], "p2wpkh");
+        assert_eq!(result["depth"], 3);
+        assert_eq!(result["child_number"], "0'");
+
+        let result = inspect("zprvAdG4iTXWBoARxkkzNpNh8r6Qag3irQB8PzEMkAFeTRXxHpbF9z4QgEvBRmfvqWvGp42t42nvgGpNgYSJA9iefm1yYNZKEm7z6qUWCroSQnE");
+        assert_eq!(result["kind"], "extended_private_key");
+        assert_eq!(result["xpub"], "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs");
+
+        let result = inspect("wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)#aaaaaaaa");
+        assert_eq!(result["script_type"], "p2wpkh");
+        assert_eq!(result["checksum_valid"], false);
+        assert_eq!(result["private"], false);
+        assert_eq!(result["keys"][0]["fingerprint"], "73c5da0a");
+        assert_eq!(result["keys"][0]["origin_path"], "m/84'/0'/0'");
+
+        let result = inspect("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(result["kind"], "address");
+        assert_eq!(result["script_type"], "p2wpkh");
+        assert_eq!(
+            result["script_pubkey"],
+            "0014c0cebcd6c3d3ca8c75dc5ec62ebe55330ef910e2"
+        );
+
+        let result = inspect("0330d54fd0dd420a6e5f8d3624f5f3482cae350f79d5f0753bf5beef9c2d91af3c");
+        assert_eq!(result["kind"], "public_key");
+
+        let result = inspect("Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp");
+        assert_eq!(result["kind"], "wif");
+        assert_eq!(result["compressed"], true);
+
+        // an empty base58check payload
+        for key in ["not a key", "3QJmnh"] {
+            assert!(Inspect {
+                key: Some(String::from(key)),
+            }
+            .inspect()
+            .is_err());
+        }
+    }
+}
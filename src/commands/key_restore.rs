@@ -1,10 +1,15 @@
+use crate::account::accounts;
 use crate::cli::Restore;
+use crate::codex32;
+use crate::commands::Primary;
+use crate::secure::{Xprv, Zeroizing};
+use crate::util::{passphrase, required, shares};
 
+use crate::error::Error;
+use bdk::bitcoin::bip32::ExtendedPrivKey;
 use bdk::bitcoin::secp256k1::Secp256k1;
 use bdk::bitcoin::Network;
-use bdk::keys::bip39::{Language, Mnemonic};
-use bdk::keys::{DerivableKey, ExtendedKey};
-use bdk::Error;
+use bdk::keys::bip39::Mnemonic;
 
 use serde_json::json;
 
@@ -12,15 +17,35 @@ impl Restore {
     pub fn restore(&self, network: Network) -> Result<serde_json::Value, Error> {
         let secp = Secp256k1::new();
 
-        let mnemonic = Mnemonic::parse_in(Language::English, self.mnemonic.clone());
-        let xkey: ExtendedKey = (mnemonic.unwrap(), self.password.clone()).into_extended_key()?;
+        let seed = if self.codex32.is_empty() {
+            let mnemonic = required(&self.mnemonic, "mnemonic")?;
+            let mnemonic = match self.language {
+                Some(language) => Mnemonic::parse_in(language, mnemonic.as_str()),
+                None => Mnemonic::parse(mnemonic.as_str()),
+            }?;
+            let password = passphrase(&self.password, "password")?;
 
-        let xprv = xkey.into_xprv(network).ok_or_else(|| {
-            Error::Generic("Privatekey info not found (should not happen)".to_string())
-        })?;
+            Zeroizing::new(mnemonic.to_seed(password.as_str()).to_vec())
+        } else {
+            // codex32 strings carry the master seed itself
+            codex32::recover(&shares(&self.codex32))?
+        };
+        let xprv = Xprv(ExtendedPrivKey::new_master(network, &seed)?);
         let fingerprint = xprv.fingerprint(&secp);
 
-        Ok(json!({ "fingerprint": fingerprint.to_string(), "xprv": xprv.to_string() }))
+        let mut result =
+            json!({ "fingerprint": fingerprint.to_string(), "xprv": xprv.to_string() });
+        if let Some(account) = self.account {
+            result["accounts"] = accounts(&xprv, account)?;
+        }
+
+        Ok(result)
+    }
+}
+
+impl Primary for Restore {
+    fn primary(&self) -> &str {
+        "xprv"
     }
 }
 
@@ -28,12 +53,19 @@ impl Restore {
 mod test {
     use super::*;
 
+    use crate::cli::Generate;
+
+    use bdk::keys::bip39::Language;
+
     #[test]
     fn test_restore() {
         // bitcoin network - without password
         let key_restore_cmd = Restore{
-            mnemonic: String::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"),
-            password: Some(String::from("")),
+            mnemonic: Some(String::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about")),
+            codex32: vec![],
+            language: None,
+            password: Some(Some(String::from(""))),
+            account: None,
         };
         let result = key_restore_cmd.restore(Network::Bitcoin).unwrap();
         let fingerprint = result.get("fingerprint").unwrap().as_str().unwrap();
@@ -42,10 +74,24 @@ mod test {
         assert_eq!(fingerprint, "73c5da0a");
         assert_eq!(xprv, "xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu");
 
+        // bitcoin network - with the xpubs of account 0
+        let key_restore_cmd = Restore {
+            mnemonic: Some(String::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about")),
+            codex32: vec![],
+            language: None,
+            password: Some(Some(String::from(""))),
+            account: Some(0),
+        };
+        let result = key_restore_cmd.restore(Network::Bitcoin).unwrap();
+        assert_eq!(result["accounts"]["bip84"]["xpub"], "[73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V");
+
         // testnet network - with password
         let key_restore_cmd = Restore{
-            mnemonic: String::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"),
-            password: Some(String::from("demo")),
+            mnemonic: Some(String::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about")),
+            codex32: vec![],
+            language: None,
+            password: Some(Some(String::from("demo"))),
+            account: None,
         };
         let result = key_restore_cmd.restore(Network::Testnet).unwrap();
         let fingerprint = result.get("fingerprint").unwrap().as_str().unwrap();
@@ -53,5 +99,82 @@ mod test {
 
         assert_eq!(fingerprint, "03393bdd");
         assert_eq!(xprv, "tprv8ZgxMBicQKsPeE6XnhCjJ5WHgod5tWXv3W3jujmxAGsgjTr8ewZan8YvomaGTDmQyYkUJuGx4XUq5czx7nytjAGSHEv1XgYqj41X3NCT3xU");
+
+        // the wordlist is detected when no language is given
+        for language in [
+            Language::English,
+            Language::Spanish,
+            Language::Japanese,
+            Language::Korean,
+        ] {
+            let key_generate_cmd = Generate {
+                dicerolls: 99,
+                rolls: None,
+                entropy: 18,
+                language,
+                password: None,
+                codex32: false,
+                threshold: 0,
+                shares: None,
+                identifier: None,
+                account: None,
+            };
+            let generated = key_generate_cmd.generate(Network::Bitcoin).unwrap();
+
+            let key_restore_cmd = Restore {
+                mnemonic: Some(
+                    generated
+                        .get("mnemonic")
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                        .to_string(),
+                ),
+                codex32: vec![],
+                language: None,
+                password: None,
+                account: None,
+            };
+            let result = key_restore_cmd.restore(Network::Bitcoin).unwrap();
+
+            assert_eq!(result.get("fingerprint"), generated.get("fingerprint"));
+            assert_eq!(result.get("xprv"), generated.get("xprv"));
+        }
+
+        // codex32 test vector 2, with a typo in the second share
+        let key_restore_cmd = Restore {
+            mnemonic: None,
+            codex32: vec![
+                String::from("MS12NAMEA320ZYXWVUTSRQPNMLKJHGFEDCAXRPP870HKKQRM"),
+                String::from("MS12NAMECACDEFGHJKLMNPQRSTUVWXYZ023FTR2GDZMPY6PM"),
+            ],
+            language: None,
+            password: None,
+            account: None,
+        };
+        let err = key_restore_cmd.restore(Network::Bitcoin).unwrap_err();
+        assert!(err.to_string().contains("position 47: 'm' -> 'n'"));
+
+        let key_restore_cmd = Restore {
+            mnemonic: None,
+            codex32: vec![
+                String::from("MS12NAMEA320ZYXWVUTSRQPNMLKJHGFEDCAXRPP870HKKQRM"),
+                String::from("MS12NAMECACDEFGHJKLMNPQRSTUVWXYZ023FTR2GDZMPY6PN"),
+            ],
+            language: None,
+            password: None,
+            account: None,
+        };
+        let result = key_restore_cmd.restore(Network::Bitcoin).unwrap();
+        let xprv = ExtendedPrivKey::new_master(
+            Network::Bitcoin,
+            &[
+                0xd1, 0x80, 0x8e, 0x09, 0x6b, 0x35, 0xb2, 0x09, 0xca, 0x12, 0x13, 0x2b, 0x26, 0x46,
+                0x62, 0xa5,
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(result.get("xprv").unwrap(), &xprv.to_string());
     }
 }
@@ -0,0 +1,108 @@
+use crate::cli::Split;
+use crate::commands::Primary;
+use crate::secure::{Xprv, Zeroizing};
+use crate::slip39;
+use crate::util::{passphrase, required};
+
+use crate::error::Error;
+use bdk::bitcoin::bip32::ExtendedPrivKey;
+use bdk::bitcoin::hashes::hex::FromHex;
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::Network;
+use bdk::keys::bip39::Mnemonic;
+
+use serde_json::json;
+
+impl Split {
+    pub fn split(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let secp = Secp256k1::new();
+
+        // the BIP39 seed is shared, so combining gives back the same master key as restoring
+        let seed = Zeroizing::new(match &self.secret {
+            Some(secret) => Vec::<u8>::from_hex(secret)?,
+            None => {
+                let mnemonic = required(&self.mnemonic, "mnemonic")?;
+                let mnemonic = Mnemonic::parse(mnemonic.as_str())?;
+                let password = passphrase(&self.password, "password")?;
+                mnemonic.to_seed(password.as_str()).to_vec()
+            }
+        });
+
+        let passphrase = passphrase(&self.passphrase, "passphrase")?;
+        let shares = slip39::split(
+            self.group_threshold,
+            &self.groups,
+            &seed,
+            passphrase.as_bytes(),
+            self.exponent,
+        )?;
+
+        let xprv = Xprv(ExtendedPrivKey::new_master(network, &seed)?);
+        let fingerprint = xprv.fingerprint(&secp);
+
+        let groups: Vec<serde_json::Value> = shares
+            .iter()
+            .zip(&self.groups)
+            .map(|(shares, (member_threshold, _))| {
+                json!({ "member_threshold": member_threshold, "shares": shares })
+            })
+            .collect();
+
+        Ok(json!({
+            "fingerprint": fingerprint.to_string(),
+            "group_threshold": self.group_threshold,
+            "groups": groups
+        }))
+    }
+}
+
+impl Primary for Split {
+    fn primary(&self) -> &str {
+        "groups.shares"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let key_split_cmd = Split {
+            mnemonic: Some(String::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about")),
+            password: None,
+            secret: None,
+            groups: vec![(2, 3), (1, 1), (3, 5)],
+            group_threshold: 2,
+            passphrase: None,
+            exponent: 0,
+        };
+        let result = key_split_cmd.split(Network::Bitcoin).unwrap();
+        let fingerprint = result.get("fingerprint").unwrap().as_str().unwrap();
+        let groups = result.get("groups").unwrap().as_array().unwrap();
+
+        assert_eq!(fingerprint, "73c5da0a");
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[2].get("member_threshold").unwrap(), 3);
+        assert_eq!(
+            groups[2].get("shares").unwrap().as_array().unwrap().len(),
+            5
+        );
+
+        // a 512 bits seed takes 59 words
+        let share = groups[0]["shares"][0].as_str().unwrap();
+        assert_eq!(share.split_whitespace().count(), 59);
+
+        // 1 of n members is rejected
+        let key_split_cmd = Split {
+            mnemonic: None,
+            password: None,
+            secret: Some(String::from("bb54aac4b89dc868ba37d9cc21b2cece")),
+            groups: vec![(1, 3)],
+            group_threshold: 1,
+            passphrase: None,
+            exponent: 0,
+        };
+        assert!(key_split_cmd.split(Network::Bitcoin).is_err());
+    }
+}
@@ -1,5 +1,22 @@
+pub mod descriptor_normalize;
+pub mod key_bip85;
 pub mod key_change;
+pub mod key_combine;
 pub mod key_derive;
 pub mod key_generate;
+pub mod key_inspect;
 pub mod key_restore;
+pub mod key_split;
+pub mod multisig_bsms;
+pub mod multisig_create;
+pub mod psbt_inspect;
+pub mod psbt_sign;
+pub mod wallet_addresses;
+pub mod wallet_balance;
 pub mod wallet_sign;
+
+/// Field of a command output printed alone by the plain and QR output formats
+pub trait Primary {
+    /// Dotted path of the field, arrays along the path are walked element by element
+    fn primary(&self) -> &str;
+}
@@ -0,0 +1,210 @@
+use crate::bsms::{decrypt, encrypt, token, DescriptorRecord, KeyRecord};
+use crate::cli::{BsmsDescriptor, BsmsKey, BsmsToken, BsmsVerify};
+use crate::commands::multisig_create::cosigners;
+use crate::commands::Primary;
+use crate::error::Error;
+use crate::secure::Xprv;
+use crate::util::{prompt, xpub};
+
+use std::str::FromStr;
+
+use bdk::bitcoin::bip32::ExtendedPrivKey;
+use bdk::bitcoin::Network;
+use bdk::descriptor::{Descriptor, DescriptorPublicKey};
+use bdk::miniscript::ForEachKey;
+
+use serde_json::json;
+
+impl BsmsToken {
+    pub fn token(&self) -> Result<serde_json::Value, Error> {
+        Ok(json!({ "token": token(self.bits)? }))
+    }
+}
+
+impl BsmsKey {
+    pub fn key_record(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let xprv = match self.xprv {
+            Some(xprv) => Xprv(xprv),
+            None => Xprv(ExtendedPrivKey::from_str(&prompt("xprv", false)?)?),
+        };
+        if xprv.network != network {
+            return Err(Error::network_mismatch(xprv.network, network));
+        }
+
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => self.script_type.account_path(network, self.account)?,
+        };
+        let record = KeyRecord::new(&self.token, &xprv, &path, &self.description)?;
+
+        Ok(json!({
+            "key": record.key,
+            "record": encrypt(&self.token, &record.to_string())?,
+        }))
+    }
+}
+
+impl BsmsDescriptor {
+    pub fn descriptor_record(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let mut signers = vec![];
+        for record in &self.records {
+            let record = KeyRecord::parse(&decrypt(&self.token, record)?)?;
+            record.verify(&self.token)?;
+            signers.push(record);
+        }
+
+        let keys: Vec<String> = signers.iter().map(|record| record.key.clone()).collect();
+        let cosigners = cosigners(&keys, self.threshold, network)?;
+        let template = self.script_type.descriptor(
+            self.threshold,
+            &cosigners
+                .iter()
+                .map(|cosigner| format!("{}/**", cosigner.key()))
+                .collect::<Vec<_>>(),
+        );
+        let record = DescriptorRecord::new(&template, network)?;
+
+        Ok(json!({
+            "signers": signers
+                .iter()
+                .map(|record| json!({ "key": record.key, "description": record.description }))
+                .collect::<Vec<_>>(),
+            "descriptor": record.descriptor,
+            "first_address": record.first_address,
+            "record": encrypt(&self.token, &record.to_string())?,
+        }))
+    }
+}
+
+impl BsmsVerify {
+    pub fn verify(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let record = DescriptorRecord::parse(&decrypt(&self.token, &self.record)?)?;
+        record.verify(network)?;
+
+        let (receive, change) = record.descriptors()?;
+
+        // the signer's own key must be one of the descriptor keys, with the same origin
+        let key = self.key.trim();
+        let (origin, xpub) = xpub(key.strip_suffix("/*").unwrap_or(key))?;
+        let included =
+            Descriptor::<DescriptorPublicKey>::from_str(&receive)?.for_any_key(|k| match k {
+                DescriptorPublicKey::XPub(k) => {
+                    k.xkey == xpub && origin.as_ref().is_none_or(|o| k.origin.as_ref() == Some(o))
+                }
+                _ => false,
+            });
+        if !included {
+            return Err(Error::Input(format!(
+                "The descriptor does not include the key {}",
+                key
+            )));
+        }
+
+        Ok(json!({
+            "descriptor": record.descriptor,
+            "first_address": record.first_address,
+            "receive": receive,
+            "change": change,
+        }))
+    }
+}
+
+impl Primary for BsmsToken {
+    fn primary(&self) -> &str {
+        "token"
+    }
+}
+
+impl Primary for BsmsKey {
+    fn primary(&self) -> &str {
+        "record"
+    }
+}
+
+impl Primary for BsmsDescriptor {
+    fn primary(&self) -> &str {
+        "record"
+    }
+}
+
+impl Primary for BsmsVerify {
+    fn primary(&self) -> &str {
+        "receive"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::account::MultisigType;
+
+    fn key_record(token: &str, xprv: &str) -> serde_json::Value {
+        BsmsKey {
+            token: token.to_string(),
+            xprv: Some(ExtendedPrivKey::from_str(xprv).unwrap()),
+            path: None,
+            script_type: MultisigType::Wsh,
+            account: 0,
+            description: String::from("coldbox"),
+        }
+        .key_record(Network::Bitcoin)
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bsms_ceremony() {
+        let token = BsmsToken { bits: 128 }.token().unwrap();
+        let token = token["token"].as_str().unwrap();
+
+        // mnemonics: "abandon ... about" and "legal winner ... yellow"
+        let first = key_record(token, "xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu");
+        let second = key_record(token, "xprv9s21ZrQH143K2x4gnzRB1eZDq92Uuvy9CXbvgQGdvykXZ9mkkot6LBjzDpgaAfvzkuxJe9JKJXQ38VoPutxvACA5MsyoBs5UyQ4HZKGshGs");
+        assert_eq!(first["key"], "[73c5da0a/48'/0'/0'/2']xpub6DkFAXWQ2dHxq2vatrt9qyA3bXYU4ToWQwCHbf5XB2mSTexcHZCeKS1VZYcPoBd5X8yVcbXFHJR9R8UCVpt82VX1VhR28mCyxUFL4r6KFrf");
+
+        let coordinator = BsmsDescriptor {
+            token: token.to_string(),
+            records: vec![
+                first["record"].as_str().unwrap().to_string(),
+                second["record"].as_str().unwrap().to_string(),
+            ],
+            threshold: 2,
+            script_type: MultisigType::Wsh,
+        };
+        let result = coordinator.descriptor_record(Network::Bitcoin).unwrap();
+        assert!(result["descriptor"]
+            .as_str()
+            .unwrap()
+            .starts_with("wsh(sortedmulti(2,[73c5da0a/48'/0'/0'/2']xpub6DkFAXWQ"));
+        assert_eq!(result["signers"][1]["description"], "coldbox");
+
+        let signer = BsmsVerify {
+            token: token.to_string(),
+            record: result["record"].as_str().unwrap().to_string(),
+            key: first["key"].as_str().unwrap().to_string(),
+        };
+        let verified = signer.verify(Network::Bitcoin).unwrap();
+        assert_eq!(verified["first_address"], result["first_address"]);
+        assert!(verified["receive"].as_str().unwrap().contains("/0/*))#"));
+
+        // the same key with the h notation, then with another origin
+        let mut signer = signer;
+        signer.key = first["key"].as_str().unwrap().replace('\'', "h");
+        assert!(signer.verify(Network::Bitcoin).is_ok());
+        signer.key = first["key"].as_str().unwrap().replace("/0'/2']", "/1'/2']");
+        assert_eq!(signer.verify(Network::Bitcoin).err().unwrap().code(), 2);
+
+        // a key record of another session is rejected
+        let other = key_record("00", "xprv9s21ZrQH143K2x4gnzRB1eZDq92Uuvy9CXbvgQGdvykXZ9mkkot6LBjzDpgaAfvzkuxJe9JKJXQ38VoPutxvACA5MsyoBs5UyQ4HZKGshGs");
+        let coordinator = BsmsDescriptor {
+            token: String::from("00"),
+            records: vec![
+                first["record"].as_str().unwrap().to_string(),
+                other["record"].as_str().unwrap().to_string(),
+            ],
+            threshold: 1,
+            script_type: MultisigType::Wsh,
+        };
+        assert!(coordinator.descriptor_record(Network::Bitcoin).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::account::{checksummed, origin_key};
+use crate::cli::Create;
+use crate::commands::Primary;
+use crate::error::Error;
+use crate::secure::Zeroizing;
+use crate::util::xpub;
+
+use bdk::bitcoin::bip32::{DerivationPath, ExtendedPubKey, Fingerprint};
+use bdk::bitcoin::Network;
+
+use serde_json::json;
+
+pub struct Cosigner {
+    pub fingerprint: Fingerprint,
+    pub path: DerivationPath,
+    pub xpub: ExtendedPubKey,
+}
+
+impl Cosigner {
+    // `[fingerprint/path]xpub`, a trailing `/*` as output by `key derive` is ignored
+    fn parse(key: &str) -> Result<Self, Error> {
+        let key = key.trim();
+        let key = key.strip_suffix("/*").unwrap_or(key);
+        let (origin, xpub) = xpub(key)?;
+        let (fingerprint, path) = origin.ok_or_else(|| {
+            Error::Input(format!("The key {} has no [fingerprint/path] origin", key))
+        })?;
+
+        Ok(Cosigner {
+            fingerprint,
+            path,
+            xpub,
+        })
+    }
+
+    /// Key with its origin, e.g. `[73c5da0a/48'/0'/0'/2']xpub...`
+    pub fn key(&self) -> String {
+        origin_key(
+            &self.fingerprint.to_string(),
+            &self.path,
+            &self.xpub.to_string(),
+        )
+    }
+}
+
+/// Parses the cosigner keys of a wallet, all of the network and distinct
+pub fn cosigners(
+    keys: &[String],
+    threshold: usize,
+    network: Network,
+) -> Result<Vec<Cosigner>, Error> {
+    let cosigners = keys
+        .iter()
+        .map(|key| Cosigner::parse(key))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    if threshold == 0 || threshold > cosigners.len() {
+        return Err(Error::Input(format!(
+            "The threshold must be between 1 and {}, the number of keys",
+            cosigners.len()
+        )));
+    }
+    for (i, cosigner) in cosigners.iter().enumerate() {
+        if cosigner.xpub.network != network {
+            return Err(Error::network_mismatch(cosigner.xpub.network, network));
+        }
+        if let Some(j) = cosigners[..i].iter().position(|c| c.xpub == cosigner.xpub) {
+            return Err(Error::Input(format!(
+                "Key {} is a duplicate of key {}",
+                i + 1,
+                j + 1
+            )));
+        }
+    }
+
+    Ok(cosigners)
+}
+
+impl Create {
+    pub fn create(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let cosigners = cosigners(&self.keys, self.threshold, network)?;
+
+        let descriptor = |chain: u32| {
+            let keys: Vec<String> = cosigners
+                .iter()
+                .map(|cosigner| format!("{}/{}/*", cosigner.key(), chain))
+                .collect();
+            checksummed(&self.script_type.descriptor(self.threshold, &keys))
+        };
+        let receive = descriptor(0)?;
+        let change = descriptor(1)?;
+
+        let specter = json!({
+            "label": self.name,
+            "blockheight": 0,
+            "descriptor": receive,
+            "devices": cosigners
+                .iter()
+                .map(|cosigner| json!({ "type": "other", "label": cosigner.fingerprint.to_string() }))
+                .collect::<Vec<_>>(),
+        });
+        let core = json!([
+            { "desc": receive, "active": true, "internal": false, "range": [0, 999], "timestamp": "now" },
+            { "desc": change, "active": true, "internal": true, "range": [0, 999], "timestamp": "now" },
+        ]);
+
+        match self.export.as_deref() {
+            Some("coldcard") => Ok(json!(self.coldcard(&cosigners)?)),
+            Some("specter") => Ok(specter),
+            Some("core") => Ok(core),
+            _ => Ok(json!({
+                "name": self.name,
+                "script_type": self.script_type.name(),
+                "threshold": self.threshold,
+                "keys": cosigners.iter().map(Cosigner::key).collect::<Vec<_>>(),
+                "receive": receive,
+                "change": change,
+                "coldcard": self.coldcard(&cosigners).ok(),
+                "specter": specter,
+                "core": core,
+            })),
+        }
+    }
+
+    /// The Coldcard multisig file alone, printed as is
+    pub fn coldcard_file(&self, network: Network) -> Result<Zeroizing<String>, Error> {
+        let cosigners = cosigners(&self.keys, self.threshold, network)?;
+        self.coldcard(&cosigners).map(Zeroizing::new)
+    }
+
+    // https://coldcard.com/docs/multisig/#configuration-file
+    fn coldcard(&self, cosigners: &[Cosigner]) -> Result<String, Error> {
+        let format = self.script_type.coldcard().ok_or_else(|| {
+            Error::Input(format!(
+                "The Coldcard multisig file does not support {}",
+                self.script_type.name()
+            ))
+        })?;
+
+        let mut lines = vec![
+            "# Coldcard multisig setup file (exported by coldbox)".to_string(),
+            "#".to_string(),
+            format!("Name: {}", self.name),
+            format!("Policy: {} of {}", self.threshold, cosigners.len()),
+        ];
+        // a single derivation line when shared, one per key otherwise
+        let shared = cosigners.iter().all(|c| c.path == cosigners[0].path);
+        if shared {
+            lines.push(format!("Derivation: {}", cosigners[0].path));
+        }
+        lines.push(format!("Format: {}", format));
+        for cosigner in cosigners {
+            lines.push(String::new());
+            if !shared {
+                lines.push(format!("Derivation: {}", cosigner.path));
+            }
+            lines.push(format!(
+                "{}: {}",
+                cosigner.fingerprint.to_string().to_uppercase(),
+                cosigner.xpub
+            ));
+        }
+
+        Ok(lines.join("\n") + "\n")
+    }
+}
+
+impl Primary for Create {
+    fn primary(&self) -> &str {
+        match self.export.as_deref() {
+            Some("specter") => "descriptor",
+            Some("core") => "desc",
+            _ => "receive",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::account::MultisigType;
+
+    // m/48'/0'/0'/2' of the "abandon ... about", "legal winner ... yellow" and "letter advice ... above" mnemonics
+    const KEYS: [&str; 3] = [
+        "[73c5da0a/48'/0'/0'/2']xpub6DkFAXWQ2dHxq2vatrt9qyA3bXYU4ToWQwCHbf5XB2mSTexcHZCeKS1VZYcPoBd5X8yVcbXFHJR9R8UCVpt82VX1VhR28mCyxUFL4r6KFrf/*",
+        "[b8688df1/48'/0'/0'/2']xpub6FQya7zGhR92kacYsNnjreouvnHJMpXYsUXnW6NJJAJRCKsa26TzDy4LdnGhEurr3d6y1J8PJ7EEMKQp74XTqYvmGJNogYXSKDszYHtF8mX/*",
+        "[28645006/48'/0'/0'/2']xpub6DnEBNkSJKBYQmsbhS1sP9cNdtU5c9PLFGCjTJmxicxc13WB8zNNGQazabQpyFAGW5bV9tMko4uBxDxjUKL6dSAcx1tEbgEHtgSqyRsekh6/*",
+    ];
+
+    fn create(keys: &[&str], script_type: MultisigType, export: Option<&str>) -> Create {
+        Create {
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            threshold: 2,
+            script_type,
+            name: String::from("vault"),
+            export: export.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_multisig_create() {
+        let result = create(&KEYS, MultisigType::Wsh, None)
+            .create(Network::Bitcoin)
+            .unwrap();
+        let receive = result["receive"].as_str().unwrap();
+        assert!(receive.starts_with("wsh(sortedmulti(2,[73c5da0a/48'/0'/0'/2']xpub6DkFAXWQ"));
+        assert!(receive.contains("/0/*))#"));
+        assert!(result["change"].as_str().unwrap().contains("/1/*))#"));
+        assert_eq!(result["specter"]["descriptor"], receive);
+        assert_eq!(result["core"][1]["internal"], true);
+
+        let coldcard = create(&KEYS, MultisigType::ShWsh, Some("coldcard"))
+            .create(Network::Bitcoin)
+            .unwrap();
+        let coldcard = coldcard.as_str().unwrap();
+        assert!(coldcard.contains(
+            "Name: vault\nPolicy: 2 of 3\nDerivation: m/48'/0'/0'/2'\nFormat: P2SH-P2WSH\n"
+        ));
+        assert!(coldcard.contains("\n73C5DA0A: xpub6DkFAXWQ"));
+
+        let result = create(&KEYS, MultisigType::Tr, None)
+            .create(Network::Bitcoin)
+            .unwrap();
+        assert!(result["receive"].as_str().unwrap().starts_with(
+            "tr(50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,multi_a(2,"
+        ));
+        assert!(result["coldcard"].is_null());
+
+        // duplicated key, network mismatch and missing origin
+        let e = create(&[KEYS[0], KEYS[1], KEYS[0]], MultisigType::Wsh, None)
+            .create(Network::Bitcoin)
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "Key 3 is a duplicate of key 1");
+        let e = create(&KEYS, MultisigType::Wsh, None)
+            .create(Network::Testnet)
+            .err()
+            .unwrap();
+        assert_eq!(e.code(), 5);
+        assert!(create(&[KEYS[0], &KEYS[1][23..]], MultisigType::Wsh, None)
+            .create(Network::Bitcoin)
+            .is_err());
+    }
+}
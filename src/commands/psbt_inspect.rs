@@ -0,0 +1,405 @@
+use crate::cli::PsbtInspect;
+use crate::commands::Primary;
+use crate::error::Error;
+use crate::secure::Zeroizing;
+
+use base64::{engine::general_purpose, Engine};
+use bdk::bitcoin::psbt::{Input, PartiallySignedTransaction};
+use bdk::bitcoin::sighash::{EcdsaSighashType, TapSighashType};
+use bdk::bitcoin::{Address, Network, Script, TxOut};
+use bdk::database::{Database, MemoryDatabase};
+use bdk::miniscript::{Legacy, Miniscript, Segwitv0};
+use bdk::{KeychainKind, Wallet};
+
+use serde_json::json;
+
+/// Sum of two amounts, an error on overflow
+pub fn add_amounts(total: u64, amount: u64) -> Result<u64, Error> {
+    total
+        .checked_add(amount)
+        .ok_or_else(|| Error::Parse("amounts overflow".to_string()))
+}
+
+/// Previous output of an input and where it comes from, the non_witness_utxo one when both are
+/// set as only its transaction is checked against the spent txid
+pub fn prevout(psbt: &PartiallySignedTransaction, index: usize) -> (Option<TxOut>, &'static str) {
+    let input = &psbt.inputs[index];
+    let vout = psbt.unsigned_tx.input[index].previous_output.vout as usize;
+    let non_witness = input
+        .non_witness_utxo
+        .as_ref()
+        .and_then(|tx| tx.output.get(vout).cloned());
+
+    match (&input.witness_utxo, non_witness) {
+        (Some(_), Some(utxo)) => (Some(utxo), "both"),
+        (Some(utxo), None) => (Some(utxo.clone()), "witness_utxo"),
+        (None, Some(utxo)) => (Some(utxo), "non_witness_utxo"),
+        (None, None) => (None, "missing"),
+    }
+}
+
+/// Whether an input spends a segwit output, native or nested in P2SH
+pub fn is_segwit(input: &Input, script: &Script) -> bool {
+    script.is_witness_program()
+        || input
+            .redeem_script
+            .as_ref()
+            .is_some_and(|script| script.is_witness_program())
+        || input.final_script_witness.is_some()
+}
+
+// legacy inputs known as such, a P2SH input without its redeem script may be nested segwit
+fn is_legacy(input: &Input, script: &Script) -> bool {
+    !is_segwit(input, script) && (!script.is_p2sh() || input.redeem_script.is_some())
+}
+
+fn push_size(len: usize) -> usize {
+    match len {
+        0..=75 => 1,
+        76..=255 => 2,
+        _ => 3,
+    }
+}
+
+fn varint_size(len: usize) -> usize {
+    match len {
+        0..=252 => 1,
+        253..=0xffff => 3,
+        _ => 5,
+    }
+}
+
+// weight added to the unsigned transaction by the scriptSig and witness of an input, the
+// signatures of the inputs not finalized yet counted at their maximum size
+fn satisfaction_weight(input: &Input, script: &Script) -> Option<usize> {
+    if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+        let script_sig = input.final_script_sig.as_ref().map_or(0, |s| s.len());
+        let witness = input
+            .final_script_witness
+            .as_ref()
+            .map_or(0, |w| w.serialized_len());
+        return Some((varint_size(script_sig) - 1 + script_sig) * 4 + witness);
+    }
+
+    let wsh = |witness_script: &Script| {
+        let ms = Miniscript::<_, Segwitv0>::parse(witness_script).ok()?;
+        let elements = ms.max_satisfaction_witness_elements().ok()?;
+        let len = witness_script.len();
+        Some(varint_size(elements) + ms.max_satisfaction_size().ok()? + varint_size(len) + len)
+    };
+
+    if script.is_p2pkh() {
+        Some(107 * 4)
+    } else if script.is_v0_p2wpkh() {
+        Some(108)
+    } else if script.is_v0_p2wsh() {
+        wsh(input.witness_script.as_ref()?)
+    } else if script.is_v1_p2tr() {
+        // key path, one more byte for a non-default sighash
+        Some(66 + input.sighash_type.map_or(0, |_| 1))
+    } else if script.is_p2sh() {
+        let redeem_script = input.redeem_script.as_ref()?;
+        let script_sig = (push_size(redeem_script.len()) + redeem_script.len()) * 4;
+        if redeem_script.is_v0_p2wpkh() {
+            Some(script_sig + 108)
+        } else if redeem_script.is_v0_p2wsh() {
+            Some(script_sig + wsh(input.witness_script.as_ref()?)?)
+        } else {
+            let ms = Miniscript::<_, Legacy>::parse(redeem_script).ok()?;
+            let len = ms.max_satisfaction_size().ok()? * 4 + script_sig;
+            Some(len + (varint_size(len / 4) - 1) * 4)
+        }
+    } else {
+        None
+    }
+}
+
+/// Name of the sighash type of an input, and whether it is SIGHASH_ALL (or DEFAULT for taproot)
+pub fn sighash(input: &Input, script: &Script) -> (String, bool) {
+    match input.sighash_type {
+        None => ("default".to_string(), true),
+        Some(sighash_type) => {
+            let standard = match script.is_v1_p2tr() {
+                true => matches!(
+                    sighash_type.taproot_hash_ty(),
+                    Ok(TapSighashType::Default | TapSighashType::All)
+                ),
+                false => matches!(sighash_type.ecdsa_hash_ty(), Ok(EcdsaSighashType::All)),
+            };
+            (sighash_type.to_string(), standard)
+        }
+    }
+}
+
+/// Inputs, outputs, fee and virtual size of a PSBT, the outputs of the wallet are marked as
+/// change when given, `external_change` when its change goes to the external addresses
+pub fn analyze(
+    psbt: &PartiallySignedTransaction,
+    network: Network,
+    wallet: Option<&Wallet<MemoryDatabase>>,
+    external_change: bool,
+) -> Result<serde_json::Value, Error> {
+    let tx = &psbt.unsigned_tx;
+
+    let mut warnings = vec![];
+    let mut inputs = vec![];
+    let mut amount_in = Some(0);
+    let mut weight = Some(tx.weight().to_wu() as usize);
+    let mut segwit = false;
+    for (i, (txin, input)) in tx.input.iter().zip(&psbt.inputs).enumerate() {
+        let (utxo, source) = prevout(psbt, i);
+
+        if let Some(prev_tx) = &input.non_witness_utxo {
+            if prev_tx.txid() != txin.previous_output.txid {
+                warnings.push(format!(
+                    "Input {} non_witness_utxo is not the transaction it spends",
+                    i
+                ));
+            }
+        }
+        if let (Some(witness_utxo), "both") = (&input.witness_utxo, source) {
+            if Some(witness_utxo) != utxo.as_ref() {
+                warnings.push(format!(
+                    "Input {} witness_utxo differs from the output of its non_witness_utxo, whose amount is used",
+                    i
+                ));
+            }
+        }
+
+        let (amount, script_type, sighash_type) = match &utxo {
+            Some(utxo) => {
+                let script = &utxo.script_pubkey;
+                if is_legacy(input, script) && input.non_witness_utxo.is_none() {
+                    warnings.push(format!(
+                        "Input {} is a legacy input without non_witness_utxo, its amount cannot be verified",
+                        i
+                    ));
+                }
+                let (sighash_type, standard) = sighash(input, script);
+                if !standard {
+                    warnings.push(format!(
+                        "Input {} uses the non-default sighash {}",
+                        i, sighash_type
+                    ));
+                }
+                segwit |= is_segwit(input, script);
+                weight = weight
+                    .zip(satisfaction_weight(input, script))
+                    .map(|(w, s)| w + s);
+
+                let script_type = Address::from_script(script, network)
+                    .ok()
+                    .and_then(|address| address.address_type())
+                    .map(|t| t.to_string());
+                (Some(utxo.value), script_type, sighash_type)
+            }
+            None => {
+                warnings.push(format!("Input {} has no UTXO data, the fee is unknown", i));
+                weight = None;
+                (None, None, "default".to_string())
+            }
+        };
+        amount_in = match (amount_in, amount) {
+            (Some(total), Some(amount)) => Some(add_amounts(total, amount)?),
+            _ => None,
+        };
+
+        inputs.push(json!({
+            "index": i,
+            "outpoint": txin.previous_output.to_string(),
+            "amount": amount,
+            "utxo": source,
+            "script_type": script_type,
+            "sighash": sighash_type,
+            "finalized": input.final_script_sig.is_some() || input.final_script_witness.is_some(),
+        }));
+    }
+
+    let mut outputs = vec![];
+    let mut amount_out = 0;
+    for (i, txout) in tx.output.iter().enumerate() {
+        amount_out = add_amounts(amount_out, txout.value)?;
+        let mut output = json!({
+            "index": i,
+            "address": Address::from_script(&txout.script_pubkey, network)
+                .ok()
+                .map(|address| address.to_string()),
+            "amount": txout.value,
+        });
+
+        if let Some(wallet) = wallet {
+            let path = wallet
+                .database()
+                .get_path_from_script_pubkey(&txout.script_pubkey)?;
+            // without a change descriptor the change goes to the external addresses
+            output["change"] = json!(match path {
+                Some((KeychainKind::Internal, _)) => true,
+                Some((KeychainKind::External, _)) => external_change,
+                None => false,
+            });
+            output["wallet"] = json!(path.map(|(keychain, index)| json!({
+                "keychain": match keychain {
+                    KeychainKind::External => "external",
+                    KeychainKind::Internal => "internal",
+                },
+                "index": index,
+            })));
+        }
+        outputs.push(output);
+    }
+
+    // segwit marker and flag
+    let vsize = weight.map(|weight| (weight + if segwit { 2 } else { 0 }).div_ceil(4));
+    let signed =
+        |amount| i64::try_from(amount).map_err(|_| Error::Parse("amounts overflow".to_string()));
+    let fee = match amount_in {
+        Some(amount_in) => Some(signed(amount_in)? - signed(amount_out)?),
+        None => None,
+    };
+    let fee_rate = fee
+        .zip(vsize)
+        .map(|(fee, vsize)| (fee as f64 / vsize as f64 * 100.0).round() / 100.0);
+
+    if let Some(fee) = fee {
+        if fee < 0 {
+            warnings.push(format!(
+                "The outputs spend {} sat more than the inputs",
+                -fee
+            ));
+        }
+    }
+    Ok(json!({
+        "txid": tx.txid().to_string(),
+        "inputs": inputs,
+        "outputs": outputs,
+        "amount_in": amount_in,
+        "amount_out": amount_out,
+        "fee": fee,
+        "fee_rate": fee_rate,
+        "vsize": vsize,
+        "warnings": warnings,
+    }))
+}
+
+impl PsbtInspect {
+    pub fn inspect(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let psbt = general_purpose::STANDARD
+            .decode(self.psbt.trim())
+            .map_err(|e| Error::Parse(format!("Invalid PSBT: {}", e)))?;
+        let psbt = PartiallySignedTransaction::deserialize(&psbt)?;
+
+        let wallet = match &self.descriptor {
+            Some(descriptor) => {
+                let descriptor = Zeroizing::new(descriptor.clone());
+                let change_descriptor = self.change_descriptor.clone().map(Zeroizing::new);
+                let wallet = Wallet::new(
+                    descriptor.as_str(),
+                    change_descriptor.as_deref().map(String::as_str),
+                    network,
+                    MemoryDatabase::default(),
+                )?;
+                wallet.ensure_addresses_cached(self.lookahead)?;
+                Some(wallet)
+            }
+            None => None,
+        };
+
+        let mut result = analyze(
+            &psbt,
+            network,
+            wallet.as_ref(),
+            self.change_descriptor.is_none(),
+        )?;
+
+        let mut warnings = vec![];
+        if let Some(fee_rate) = result["fee_rate"]
+            .as_f64()
+            .filter(|rate| *rate > self.max_fee_rate)
+        {
+            warnings.push(json!(format!(
+                "The fee rate of {} sat/vB is above {} sat/vB",
+                fee_rate, self.max_fee_rate
+            )));
+        }
+        if let (Some(fee), Some(amount_in)) = (result["fee"].as_i64(), result["amount_in"].as_u64())
+        {
+            let percent = fee as f64 / amount_in as f64 * 100.0;
+            if amount_in > 0 && percent > self.max_fee_percent {
+                warnings.push(json!(format!(
+                    "The fee of {} sat is {:.2}% of the spent amount",
+                    fee, percent
+                )));
+            }
+        }
+
+        if let Some(list) = result["warnings"].as_array_mut() {
+            list.extend(warnings);
+        }
+
+        Ok(result)
+    }
+}
+
+impl Primary for PsbtInspect {
+    fn primary(&self) -> &str {
+        "fee"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_psbt_inspect() {
+        let inspect_cmd = PsbtInspect {
+            psbt: String::from("cHNidP8BAFICAAAAAQ3TM54hf/xyGNQ3RwZ9zykQsbogN20RNgReU5yir1+IAQAAAAD9////ARAnAAAAAAAAFgAUGFotGcPnrJnJg8Mz1Htu+ejz1V8JwiMATwEENYf\
+                PAw70sa+AAAAAPIwgN+5MFiHaDTSNtRFjcJpiLQ0oON3m2EGcUfYwHGIDuI4Pvj9kYzftk7wMDzuEP899JYnl7IhHVOZAICeokLQQc8XaClQAAIABAACAAAAAgAABAHECAAAAAYDXHmXP+m\
+                71ecUGq9jRgehhR8fuWtYc89I8qZQE2zemAQAAAAD9////AlKoYwAAAAAAFgAUQsjMYy/RsZmttmBmh8tm3sjDdRudJwAAAAAAABYAFPfWsQ9R/oRyjJCODiuUa9dl46c/A8IjAAEBH50nA\
+                AAAAAAAFgAU99axD1H+hHKMkI4OK5Rr12Xjpz8BAwQBAAAAIgYC/0PHIY//lNUR6ikHqAV5i0XvloIezNiePTxpkR+h7SEYc8XaClQAAIABAACAAAAAgAAAAAASAAAAAAA="),
+            descriptor: Some(String::from("wpkh([73c5da0a/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/0/*)")),
+            change_descriptor: Some(String::from("wpkh([73c5da0a/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/1/*)")),
+            lookahead: 100,
+            max_fee_rate: 500.0,
+            max_fee_percent: 10.0,
+        };
+        let result = inspect_cmd.inspect(Network::Testnet).unwrap();
+
+        assert_eq!(result["inputs"][0]["amount"], 10141);
+        assert_eq!(result["inputs"][0]["utxo"], "both");
+        assert_eq!(result["inputs"][0]["script_type"], "p2wpkh");
+        assert_eq!(result["inputs"][0]["sighash"], "SIGHASH_ALL");
+        assert_eq!(result["outputs"][0]["amount"], 10000);
+        assert_eq!(result["outputs"][0]["change"], false);
+        assert_eq!(result["fee"], 141);
+        assert_eq!(result["vsize"], 110);
+        assert_eq!(result["fee_rate"], 1.28);
+        assert_eq!(result["warnings"].as_array().unwrap().len(), 0);
+
+        // a witness_utxo lying about the amount
+        let mut psbt = PartiallySignedTransaction::deserialize(
+            &general_purpose::STANDARD.decode(&inspect_cmd.psbt).unwrap(),
+        )
+        .unwrap();
+        psbt.inputs[0].witness_utxo.as_mut().unwrap().value = 1;
+        let result = analyze(&psbt, Network::Testnet, None, false).unwrap();
+        assert_eq!(result["inputs"][0]["amount"], 10141);
+        assert_eq!(result["fee"], 141);
+        assert!(result["warnings"][0]
+            .as_str()
+            .unwrap()
+            .contains("witness_utxo differs"));
+
+        // outputs summing above u64::MAX
+        let mut psbt = PartiallySignedTransaction::deserialize(
+            &general_purpose::STANDARD.decode(&inspect_cmd.psbt).unwrap(),
+        )
+        .unwrap();
+        let mut txout = psbt.unsigned_tx.output[0].clone();
+        txout.value = u64::MAX;
+        psbt.unsigned_tx.output.push(txout);
+        psbt.outputs.push(Default::default());
+        let e = analyze(&psbt, Network::Testnet, None, false).err().unwrap();
+        assert_eq!(e.to_string(), "amounts overflow");
+    }
+}
@@ -0,0 +1,265 @@
+use crate::account::origin_key;
+use crate::cli::PsbtSign;
+use crate::commands::psbt_inspect::{is_segwit, prevout, sighash};
+use crate::commands::Primary;
+use crate::error::Error;
+use crate::policy::{Policy, Signer};
+use crate::secure::{Xprv, Zeroizing};
+use crate::util::{passphrase, required};
+
+use base64::{engine::general_purpose, Engine};
+use bdk::bitcoin::bip32::{DerivationPath, ExtendedPrivKey, Fingerprint};
+use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bdk::bitcoin::{Network, PrivateKey, PublicKey};
+use bdk::keys::bip39::Mnemonic;
+use bdk::miniscript::psbt::PsbtExt;
+use bdk::signer::{InputSigner, SignerContext, SignerError, SignerWrapper};
+use bdk::SignOptions;
+
+use serde_json::json;
+
+// key of an input to sign, from its bip32_derivation or tap_key_origins
+enum Origin {
+    Ecdsa(PublicKey, DerivationPath),
+    Schnorr(XOnlyPublicKey, DerivationPath),
+}
+
+// keys of the input derived from the master key
+fn origins(
+    psbt: &PartiallySignedTransaction,
+    index: usize,
+    fingerprint: Fingerprint,
+) -> Vec<Origin> {
+    let input = &psbt.inputs[index];
+    let ecdsa = input
+        .bip32_derivation
+        .iter()
+        .filter(|(_, (f, _))| *f == fingerprint)
+        .map(|(key, (_, path))| Origin::Ecdsa(PublicKey::new(*key), path.clone()));
+    let schnorr = input
+        .tap_key_origins
+        .iter()
+        .filter(|(_, (_, (f, _)))| *f == fingerprint)
+        .map(|(key, (_, (_, path)))| Origin::Schnorr(*key, path.clone()));
+
+    ecdsa.chain(schnorr).collect()
+}
+
+impl PsbtSign {
+    pub fn sign(&self, network: Network) -> Result<serde_json::Value, Error> {
+        let secp = Secp256k1::new();
+
+        let xprv = match &self.xprv {
+            Some(xprv) => {
+                if xprv.network != network {
+                    return Err(Error::network_mismatch(xprv.network, network));
+                }
+                if xprv.depth != 0 {
+                    return Err(Error::Input(format!(
+                        "The xprv must be a master key, its depth is {}",
+                        xprv.depth
+                    )));
+                }
+                Xprv(*xprv)
+            }
+            None => {
+                let mnemonic = required(&self.mnemonic, "mnemonic")?;
+                let mnemonic = Mnemonic::parse(mnemonic.as_str())?;
+                let password = passphrase(&self.password, "password")?;
+                let seed = Zeroizing::new(mnemonic.to_seed(password.as_str()));
+                Xprv(ExtendedPrivKey::new_master(network, &*seed)?)
+            }
+        };
+        let fingerprint = xprv.fingerprint(&secp);
+
+        let psbt = general_purpose::STANDARD
+            .decode(self.psbt.trim())
+            .map_err(|e| Error::Parse(format!("Invalid PSBT: {}", e)))?;
+        let mut psbt = PartiallySignedTransaction::deserialize(&psbt)?;
+
+        // nothing is signed when the policy is broken
+        if let Some(path) = &self.policy {
+            let violations = Policy::load(path)?.check(&psbt, network, Signer::Master(&xprv))?;
+            if !violations.is_empty() {
+                return Err(Error::Policy(violations));
+            }
+        }
+
+        let options = SignOptions {
+            trust_witness_utxo: self.trust_witness_utxo,
+            allow_all_sighashes: self.allow_all_sighashes,
+            ..Default::default()
+        };
+
+        let mut signed = vec![];
+        for i in 0..psbt.inputs.len() {
+            let input = &psbt.inputs[i];
+            let origins = origins(&psbt, i, fingerprint);
+            if origins.is_empty()
+                || input.final_script_sig.is_some()
+                || input.final_script_witness.is_some()
+            {
+                continue;
+            }
+
+            // the checks bdk does before signing with a wallet
+            let utxo = prevout(&psbt, i).0.ok_or(SignerError::MissingWitnessUtxo)?;
+            let taproot = utxo.script_pubkey.is_v1_p2tr();
+            if !taproot && input.non_witness_utxo.is_none() && !options.trust_witness_utxo {
+                return Err(SignerError::MissingNonWitnessUtxo.into());
+            }
+            if !sighash(input, &utxo.script_pubkey).1 && !options.allow_all_sighashes {
+                return Err(SignerError::NonStandardSighash.into());
+            }
+            let segwit = is_segwit(input, &utxo.script_pubkey);
+            let internal_key = input.tap_internal_key;
+
+            for origin in origins {
+                let (path, taproot_key) = match &origin {
+                    Origin::Ecdsa(_, path) => (path, None),
+                    Origin::Schnorr(key, path) => (path, Some(*key)),
+                };
+                let derived = Xprv(xprv.derive_priv(&secp, path)?);
+                let private_key = PrivateKey::new(derived.private_key, network);
+                let public_key = PublicKey::from_private_key(&secp, &private_key);
+
+                let (key, context) = match origin {
+                    Origin::Ecdsa(key, _) => (
+                        key.to_string(),
+                        match segwit {
+                            true => SignerContext::Segwitv0,
+                            false => SignerContext::Legacy,
+                        },
+                    ),
+                    Origin::Schnorr(key, _) => (
+                        key.to_string(),
+                        SignerContext::Tap {
+                            is_internal_key: internal_key == Some(key),
+                        },
+                    ),
+                };
+                let matches = match taproot_key {
+                    Some(x_only) => XOnlyPublicKey::from(public_key.inner) == x_only,
+                    None => key == public_key.to_string(),
+                };
+                if !matches {
+                    return Err(SignerError::InvalidKey.into());
+                }
+
+                let before = json!([
+                    psbt.inputs[i].partial_sigs.len(),
+                    psbt.inputs[i].tap_key_sig.is_some(),
+                    psbt.inputs[i].tap_script_sigs.len(),
+                ]);
+                SignerWrapper::new(private_key, context)
+                    .sign_input(&mut psbt, i, &options, &secp)?;
+                let after = json!([
+                    psbt.inputs[i].partial_sigs.len(),
+                    psbt.inputs[i].tap_key_sig.is_some(),
+                    psbt.inputs[i].tap_script_sigs.len(),
+                ]);
+                if before != after {
+                    signed.push(json!({
+                        "input": i,
+                        "key": origin_key(&fingerprint.to_string(), path, &key),
+                    }));
+                }
+            }
+        }
+
+        if signed.is_empty() {
+            return Err(Error::Signing(format!(
+                "No input of the PSBT has a key of the master key {}",
+                fingerprint
+            )));
+        }
+
+        // inputs missing signatures of other keys are left as they are
+        if !self.no_finalize {
+            for i in 0..psbt.inputs.len() {
+                let _ = psbt.finalize_inp_mut(&secp, i);
+            }
+        }
+        let finalized = psbt
+            .inputs
+            .iter()
+            .all(|input| input.final_script_sig.is_some() || input.final_script_witness.is_some());
+
+        Ok(json!({
+            "is_finalized": finalized,
+            "psbt": general_purpose::STANDARD.encode(psbt.serialize()),
+            "signed": signed,
+        }))
+    }
+}
+
+impl Primary for PsbtSign {
+    fn primary(&self) -> &str {
+        "psbt"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::str::FromStr;
+
+    fn sign_cmd(xprv: Option<&str>, mnemonic: Option<&str>) -> PsbtSign {
+        PsbtSign {
+            // the PSBT of the wallet sign test, its input is m/84'/1'/0'/0/18
+            psbt: String::from("cHNidP8BAFICAAAAAQ3TM54hf/xyGNQ3RwZ9zykQsbogN20RNgReU5yir1+IAQAAAAD9////ARAnAAAAAAAAFgAUGFotGcPnrJnJg8Mz1Htu+ejz1V8JwiMATwEENYfPAw70sa+AAAAAPIwgN+5MFiHaDTSNtRFjcJpiLQ0oON3m2EGcUfYwHGIDuI4Pvj9kYzftk7wMDzuEP899JYnl7IhHVOZAICeokLQQc8XaClQAAIABAACAAAAAgAABAHECAAAAAYDXHmXP+m71ecUGq9jRgehhR8fuWtYc89I8qZQE2zemAQAAAAD9////AlKoYwAAAAAAFgAUQsjMYy/RsZmttmBmh8tm3sjDdRudJwAAAAAAABYAFPfWsQ9R/oRyjJCODiuUa9dl46c/A8IjAAEBH50nAAAAAAAAFgAU99axD1H+hHKMkI4OK5Rr12Xjpz8BAwQBAAAAIgYC/0PHIY//lNUR6ikHqAV5i0XvloIezNiePTxpkR+h7SEYc8XaClQAAIABAACAAAAAgAAAAAASAAAAAAA="),
+            xprv: xprv.map(|xprv| ExtendedPrivKey::from_str(xprv).unwrap()),
+            mnemonic: mnemonic.map(String::from),
+            password: Some(Some(String::new())),
+            policy: None,
+            trust_witness_utxo: false,
+            allow_all_sighashes: false,
+            no_finalize: false,
+        }
+    }
+
+    #[test]
+    fn test_psbt_sign() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let result = sign_cmd(None, Some(mnemonic))
+            .sign(Network::Testnet)
+            .unwrap();
+        assert_eq!(result["is_finalized"], true);
+        assert_eq!(
+            result["signed"],
+            json!([{
+                "input": 0,
+                "key": "[73c5da0a/84'/1'/0'/0/18]02ff43c7218fff94d511ea2907a805798b45ef96821eccd89e3d3c69911fa1ed21",
+            }])
+        );
+        // the signature of the descriptor wallet, the finalized input keeps only its UTXOs
+        assert_eq!(result["psbt"], "cHNidP8BAFICAAAAAQ3TM54hf/xyGNQ3RwZ9zykQsbogN20RNgReU5yir1+IAQAAAAD9////ARAnAAAAAAAAFgAUGFotGcPnrJnJg8Mz1Htu+ejz1V8JwiMATwEENYfPAw70sa+AAAAAPIwgN+5MFiHaDTSNtRFjcJpiLQ0oON3m2EGcUfYwHGIDuI4Pvj9kYzftk7wMDzuEP899JYnl7IhHVOZAICeokLQQc8XaClQAAIABAACAAAAAgAABAHECAAAAAYDXHmXP+m71ecUGq9jRgehhR8fuWtYc89I8qZQE2zemAQAAAAD9////AlKoYwAAAAAAFgAUQsjMYy/RsZmttmBmh8tm3sjDdRudJwAAAAAAABYAFPfWsQ9R/oRyjJCODiuUa9dl46c/A8IjAAEBH50nAAAAAAAAFgAU99axD1H+hHKMkI4OK5Rr12Xjpz8BCGsCRzBEAiArgxHk+RWdmg6uJkBRetk0fQVHxoxYI5yL3W8Ipxc8yQIgWE/OlFkBJpy3V6N+qO1nQFz9ysRNcgoFrBdgDJ+w7QYBIQL/Q8chj/+U1RHqKQeoBXmLRe+Wgh7M2J49PGmRH6HtIQAA");
+
+        // the root xprv of the mnemonic
+        let result = sign_cmd(Some("tprv8ZgxMBicQKsPe5YMU9gHen4Ez3ApihUfykaqUorj9t6FDqy3nP6eoXiAo2ssvpAjoLroQxHqr3R5nE3a5dU3DHTjTgJDd7zrbniJr6nrCzd"), None)
+            .sign(Network::Testnet)
+            .unwrap();
+        assert_eq!(result["signed"][0]["input"], 0);
+
+        // the master key of "legal winner ... yellow" has no key in the PSBT
+        let e = sign_cmd(
+            None,
+            Some("legal winner thank year wave sausage worth useful legal winner thank yellow"),
+        )
+        .sign(Network::Testnet)
+        .err()
+        .unwrap();
+        assert_eq!(e.code(), 6);
+
+        // the policy is checked before signing
+        let path = std::env::temp_dir().join(format!("coldbox-policy-{}", std::process::id()));
+        std::fs::write(&path, r#"{"max_amount": 5000}"#).unwrap();
+        let mut cmd = sign_cmd(None, Some(mnemonic));
+        cmd.policy = Some(path.to_string_lossy().to_string());
+        let e = cmd.sign(Network::Testnet).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(e.code(), 7);
+    }
+}
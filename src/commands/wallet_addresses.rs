@@ -0,0 +1,237 @@
+use crate::cli::Addresses;
+use crate::commands::Primary;
+use crate::secure::Zeroizing;
+use crate::util::verify_checksum;
+
+use crate::error::Error;
+use bdk::bitcoin::secp256k1::{Secp256k1, VerifyOnly};
+use bdk::bitcoin::Network;
+use bdk::descriptor::{Descriptor, DescriptorPublicKey};
+use bdk::miniscript::ForEachKey;
+
+use rayon::prelude::*;
+use serde_json::json;
+
+struct Entry {
+    keychain: &'static str,
+    index: u32,
+    paths: Vec<String>,
+    address: String,
+    script_pubkey: String,
+    public_keys: Vec<String>,
+}
+
+// one value for single key descriptors, a list otherwise
+fn one_or_many(values: &[String]) -> serde_json::Value {
+    match values {
+        [value] => json!(value),
+        values => json!(values),
+    }
+}
+
+fn derive(
+    secp: &Secp256k1<VerifyOnly>,
+    descriptor: &Descriptor<DescriptorPublicKey>,
+    keychain: &'static str,
+    index: u32,
+    network: Network,
+) -> Result<Entry, Error> {
+    let derived = descriptor
+        .at_derivation_index(index)
+        .map_err(|e| Error::Input(e.to_string()))?;
+
+    let mut paths = vec![];
+    let mut public_keys = vec![];
+    let mut result = Ok(());
+    derived.for_each_key(|key| {
+        if let Some(path) = key.full_derivation_path() {
+            paths.push(path.to_string());
+        }
+        match key.derive_public_key(secp) {
+            Ok(public_key) => public_keys.push(public_key.to_string()),
+            Err(e) => result = Err(Error::Parse(e.to_string())),
+        }
+        true
+    });
+    result?;
+
+    Ok(Entry {
+        keychain,
+        index,
+        paths,
+        address: derived.address(network)?.to_string(),
+        script_pubkey: derived.script_pubkey().to_hex_string(),
+        public_keys,
+    })
+}
+
+impl Addresses {
+    // addresses of both keychains, derived in parallel
+    fn entries(
+        &self,
+        network: Network,
+        descriptor: Zeroizing<String>,
+        change_descriptor: Option<Zeroizing<String>>,
+    ) -> Result<Vec<Entry>, Error> {
+        verify_checksum(&descriptor)?;
+        if let Some(change_descriptor) = &change_descriptor {
+            verify_checksum(change_descriptor)?;
+        }
+
+        // private keys are dropped right away, only the public descriptors are needed
+        let secp = Secp256k1::new();
+        let mut keychains = vec![(
+            "external",
+            Descriptor::parse_descriptor(&secp, &descriptor)?.0,
+        )];
+        if let Some(change_descriptor) = &change_descriptor {
+            keychains.push((
+                "internal",
+                Descriptor::parse_descriptor(&secp, change_descriptor)?.0,
+            ));
+        }
+
+        let end = self
+            .start
+            .checked_add(self.count)
+            .ok_or_else(|| Error::Input("The index range overflows".to_string()))?;
+
+        let secp = Secp256k1::verification_only();
+        keychains
+            .iter()
+            .flat_map(|(keychain, descriptor)| {
+                (self.start..end).map(move |i| (*keychain, descriptor, i))
+            })
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(keychain, descriptor, index)| {
+                derive(&secp, descriptor, keychain, index, network)
+            })
+            .collect::<Result<Vec<Entry>, Error>>()
+    }
+
+    pub fn addresses(
+        &self,
+        network: Network,
+        descriptor: Zeroizing<String>,
+        change_descriptor: Option<Zeroizing<String>>,
+    ) -> Result<serde_json::Value, Error> {
+        let entries = self.entries(network, descriptor, change_descriptor)?;
+
+        Ok(json!(entries
+            .iter()
+            .map(|entry| json!({
+                "keychain": entry.keychain,
+                "index": entry.index,
+                "path": one_or_many(&entry.paths),
+                "address": entry.address,
+                "script_pubkey": entry.script_pubkey,
+                "public_key": one_or_many(&entry.public_keys),
+            }))
+            .collect::<Vec<_>>()))
+    }
+
+    /// The addresses as CSV text, one line per address after the header
+    pub fn csv(
+        &self,
+        network: Network,
+        descriptor: Zeroizing<String>,
+        change_descriptor: Option<Zeroizing<String>>,
+    ) -> Result<Zeroizing<String>, Error> {
+        let entries = self.entries(network, descriptor, change_descriptor)?;
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        let mut write = || -> Result<Vec<u8>, csv::Error> {
+            writer.write_record([
+                "keychain",
+                "index",
+                "path",
+                "address",
+                "script_pubkey",
+                "public_key",
+            ])?;
+            for entry in &entries {
+                writer.write_record([
+                    entry.keychain,
+                    &entry.index.to_string(),
+                    &entry.paths.join(";"),
+                    &entry.address,
+                    &entry.script_pubkey,
+                    &entry.public_keys.join(";"),
+                ])?;
+            }
+            writer.flush()?;
+            Ok(writer.get_ref().clone())
+        };
+        let csv = write().map_err(|e| Error::Output(format!("Cannot write the CSV: {}", e)))?;
+
+        Ok(Zeroizing::new(String::from_utf8_lossy(&csv).to_string()))
+    }
+}
+
+impl Primary for Addresses {
+    fn primary(&self) -> &str {
+        "address"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_addresses() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki#test-vectors
+        let descriptor = Zeroizing::new(String::from("wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)"));
+        let change_descriptor = Zeroizing::new(String::from("wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/1/*)"));
+
+        let addresses_cmd = Addresses {
+            start: 0,
+            count: 2,
+            format: String::from("json"),
+        };
+        let result = addresses_cmd
+            .addresses(
+                Network::Bitcoin,
+                descriptor.clone(),
+                Some(change_descriptor),
+            )
+            .unwrap();
+        let entries = result.as_array().unwrap();
+
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0]["path"], "m/84'/0'/0'/0/0");
+        assert_eq!(
+            entries[0]["address"],
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        assert_eq!(
+            entries[0]["public_key"],
+            "0330d54fd0dd420a6e5f8d3624f5f3482cae350f79d5f0753bf5beef9c2d91af3c"
+        );
+        assert_eq!(
+            entries[0]["script_pubkey"],
+            "0014c0cebcd6c3d3ca8c75dc5ec62ebe55330ef910e2"
+        );
+        assert_eq!(
+            entries[1]["address"],
+            "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g"
+        );
+        assert_eq!(entries[2]["keychain"], "internal");
+        assert_eq!(
+            entries[2]["address"],
+            "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"
+        );
+
+        let addresses_cmd = Addresses {
+            start: 1,
+            count: 1,
+            format: String::from("csv"),
+        };
+        let result = addresses_cmd
+            .csv(Network::Bitcoin, descriptor, None)
+            .unwrap();
+
+        assert_eq!(*result, "keychain,index,path,address,script_pubkey,public_key\nexternal,1,m/84'/0'/0'/0/1,bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g,00149c90f934ea51fa0f6504177043e0908da6929983,03e775fd51f0dfb8cd865d9ff1cca2a158cf651fe997fdc9fee9c1d3b5e995ea77\n");
+    }
+}
@@ -0,0 +1,204 @@
+use crate::cli::Balance;
+use crate::commands::Primary;
+use crate::error::Error;
+use crate::secure::Zeroizing;
+
+use bdk::bitcoin::Network;
+use bdk::blockchain::ElectrumBlockchain;
+use bdk::database::MemoryDatabase;
+use bdk::electrum_client::Client;
+use bdk::{SyncOptions, Wallet};
+
+use serde_json::json;
+
+impl Balance {
+    pub fn balance(
+        &self,
+        network: Network,
+        descriptor: Zeroizing<String>,
+        change_descriptor: Option<Zeroizing<String>>,
+        server: String,
+    ) -> Result<serde_json::Value, Error> {
+        let wallet = Wallet::new(
+            descriptor.as_str(),
+            change_descriptor.as_deref().map(String::as_str),
+            network,
+            MemoryDatabase::default(),
+        )?;
+
+        let client = Client::new(&server)?;
+        let blockchain = ElectrumBlockchain::from(client);
+        wallet.sync(&blockchain, SyncOptions::default())?;
+
+        let balance = wallet.get_balance()?;
+
+        Ok(json!({
+            "confirmed": balance.confirmed,
+            "trusted_pending": balance.trusted_pending,
+            "untrusted_pending": balance.untrusted_pending,
+            "immature": balance.immature,
+        }))
+    }
+}
+
+impl Primary for Balance {
+    fn primary(&self) -> &str {
+        "confirmed"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::thread;
+
+    use bdk::bitcoin::absolute::LockTime;
+    use bdk::bitcoin::block::{Header, Version};
+    use bdk::bitcoin::consensus::serialize;
+    use bdk::bitcoin::hash_types::TxMerkleNode;
+    use bdk::bitcoin::hashes::{sha256, Hash};
+    use bdk::bitcoin::{
+        BlockHash, CompactTarget, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+        Witness,
+    };
+    use bdk::wallet::AddressIndex;
+
+    fn tx(input: OutPoint, outputs: Vec<(ScriptBuf, u64)>) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: input,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: outputs
+                .into_iter()
+                .map(|(script_pubkey, value)| TxOut {
+                    value,
+                    script_pubkey,
+                })
+                .collect(),
+        }
+    }
+
+    fn scripthash(script: &ScriptBuf) -> String {
+        let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+        hash.reverse();
+        hash.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // Minimal line-based JSON-RPC server answering the calls made by a BDK Electrum sync
+    fn mock_electrum(
+        history: HashMap<String, Vec<(Txid, i32)>>,
+        txs: Vec<Transaction>,
+        tip: u32,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+
+        let header = serialize(&Header {
+            version: Version::TWO,
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: 1_700_000_000,
+            bits: CompactTarget::from_consensus(0x207fffff),
+            nonce: 0,
+        });
+        let header_hex: String = header.iter().map(|b| format!("{:02x}", b)).collect();
+        let txs: HashMap<String, String> = txs
+            .iter()
+            .map(|tx| {
+                let raw: String = serialize(tx).iter().map(|b| format!("{:02x}", b)).collect();
+                (tx.txid().to_string(), raw)
+            })
+            .collect();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { break };
+                let req: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let param = req["params"][0].as_str().unwrap_or_default().to_string();
+
+                let result = match req["method"].as_str().unwrap() {
+                    "blockchain.headers.subscribe" => json!({ "height": tip, "hex": header_hex }),
+                    "blockchain.block.header" => json!(header_hex),
+                    "blockchain.transaction.get" => json!(txs[&param]),
+                    "blockchain.scripthash.get_history" => json!(history
+                        .get(&param)
+                        .map(|txs| txs
+                            .iter()
+                            .map(|(txid, height)| json!({ "tx_hash": txid, "height": height }))
+                            .collect::<Vec<_>>())
+                        .unwrap_or_default()),
+                    method => panic!("unexpected method {}", method),
+                };
+
+                let resp = json!({ "jsonrpc": "2.0", "id": req["id"], "result": result });
+                writeln!(writer, "{}", resp).unwrap();
+            }
+        });
+
+        url
+    }
+
+    #[test]
+    fn test_balance() {
+        // mnemonic: abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about
+        // path: "m/84'/1'/0'"
+        let descriptor = Zeroizing::new(String::from("wpkh([73c5da0a/84'/1'/0']tprv8fSjiqEQ8YG7Ro7gw2ScwcvweYuuWi1ZzGUtrPz918HvDtBzL5s2voFTrN4y3yUwj5cYD54pLhxk6NKCzHUjcka3zbKjbTEcsuAnkzbjhkL/0/*)"));
+
+        let wallet = Wallet::new(
+            descriptor.as_str(),
+            None,
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap();
+        let script = wallet
+            .get_address(AddressIndex::Peek(0))
+            .unwrap()
+            .script_pubkey();
+
+        let funding = tx(
+            OutPoint::new(
+                Txid::from_str("88af5fa2ce9c535e043611eb0e2037bab21029cf7d064737d41872fc7f219e33")
+                    .unwrap(),
+                0,
+            ),
+            vec![(ScriptBuf::new(), 100_000), (ScriptBuf::new(), 100_000)],
+        );
+        let confirmed = tx(
+            OutPoint::new(funding.txid(), 0),
+            vec![(script.clone(), 50_000)],
+        );
+        let pending = tx(
+            OutPoint::new(funding.txid(), 1),
+            vec![(script.clone(), 10_000)],
+        );
+
+        let history = HashMap::from([(
+            scripthash(&script),
+            vec![(confirmed.txid(), 100), (pending.txid(), 0)],
+        )]);
+        let server = mock_electrum(history, vec![funding, confirmed, pending], 200);
+
+        let balance_cmd = Balance {};
+        let result = balance_cmd
+            .balance(Network::Testnet, descriptor, None, server)
+            .unwrap();
+
+        assert_eq!(result.get("confirmed").unwrap(), 50_000);
+        assert_eq!(result.get("trusted_pending").unwrap(), 0);
+        assert_eq!(result.get("untrusted_pending").unwrap(), 10_000);
+        assert_eq!(result.get("immature").unwrap(), 0);
+    }
+}
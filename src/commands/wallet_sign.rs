@@ -1,26 +1,85 @@
 use crate::cli::Sign;
+use crate::commands::Primary;
+use crate::error::Error;
+use crate::policy::{Policy, Signer};
+use crate::secure::Zeroizing;
 
-use base64::{Engine, engine::general_purpose};
-use bdk::bitcoin::Network;
-use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use base64::{engine::general_purpose, Engine};
+use bdk::bitcoin::psbt::{Input, PartiallySignedTransaction};
+use bdk::bitcoin::{Network, ScriptBuf};
 use bdk::database::MemoryDatabase;
-use bdk::{Error, SignOptions, Wallet};
+use bdk::{SignOptions, Wallet};
 
 use serde_json::json;
 
 impl Sign {
     pub fn sign(
-        self,
+        &self,
         network: Network,
-        descriptor: String,
+        descriptor: Zeroizing<String>,
+        change_descriptor: Option<Zeroizing<String>>,
         verbose: bool,
     ) -> Result<serde_json::Value, Error> {
-        let wallet = Wallet::new(&descriptor, None, network, MemoryDatabase::default())?;
+        let wallet = Wallet::new(
+            descriptor.as_str(),
+            change_descriptor.as_deref().map(String::as_str),
+            network,
+            MemoryDatabase::default(),
+        )?;
 
-        let psbt = general_purpose::STANDARD.decode(&self.psbt).unwrap();
+        let psbt = general_purpose::STANDARD
+            .decode(&self.psbt)
+            .map_err(|e| Error::Parse(format!("Invalid PSBT: {}", e)))?;
         let mut psbt = PartiallySignedTransaction::deserialize(&psbt)?;
 
-        let finalized = wallet.sign(&mut psbt, SignOptions::default())?;
+        // nothing is signed when the policy is broken
+        if let Some(path) = &self.policy {
+            let violations = Policy::load(path)?.check(&psbt, network, Signer::Wallet(&wallet))?;
+            if !violations.is_empty() {
+                return Err(Error::Policy(violations));
+            }
+        }
+
+        let options = SignOptions {
+            trust_witness_utxo: self.trust_witness_utxo,
+            assume_height: self.assume_height,
+            allow_all_sighashes: self.allow_all_sighashes,
+            try_finalize: !self.no_finalize,
+            tap_leaves_options: self.tap_leaves.clone(),
+            sign_with_tap_internal_key: !self.no_tap_internal_key,
+            allow_grinding: !self.no_grinding,
+            ..Default::default()
+        };
+
+        let finalized = match self.inputs.is_empty() {
+            true => wallet.sign(&mut psbt, options)?,
+            // the other inputs are left out as finalized ones, bdk neither checks nor signs them
+            false => {
+                if let Some(index) = self.inputs.iter().find(|&&i| i >= psbt.inputs.len()) {
+                    return Err(Error::Input(format!(
+                        "The PSBT has no input {}, it has {} inputs",
+                        index,
+                        psbt.inputs.len()
+                    )));
+                }
+                let mut signed = psbt.clone();
+                for (i, input) in signed.inputs.iter_mut().enumerate() {
+                    if !self.inputs.contains(&i) {
+                        *input = Input {
+                            final_script_sig: Some(ScriptBuf::new()),
+                            ..Default::default()
+                        };
+                    }
+                }
+                wallet.sign(&mut signed, options)?;
+                for &i in &self.inputs {
+                    psbt.inputs[i] = signed.inputs[i].clone();
+                }
+                psbt.inputs.iter().all(|input| {
+                    input.final_script_sig.is_some() || input.final_script_witness.is_some()
+                })
+            }
+        };
 
         let psbt_base64 = general_purpose::STANDARD.encode(psbt.serialize());
 
@@ -38,35 +97,136 @@ impl Sign {
     }
 }
 
+impl Primary for Sign {
+    fn primary(&self) -> &str {
+        "psbt"
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use bdk::bitcoin::sighash::EcdsaSighashType;
+    use bdk::signer::TapLeavesOptions;
+
     #[test]
     fn test_sign() {
         // mnemonic: abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about
         // path: "m/84'/1'/0'"
-        let descriptor = String::from("wpkh([73c5da0a/84'/1'/0']tprv8fSjiqEQ8YG7Ro7gw2ScwcvweYuuWi1ZzGUtrPz918HvDtBzL5s2voFTrN4y3yUwj5cYD54pLhxk6NKCzHUjcka3zbK\
-            jbTEcsuAnkzbjhkL/0/*)");
+        let descriptor = Zeroizing::new(String::from("wpkh([73c5da0a/84'/1'/0']tprv8fSjiqEQ8YG7Ro7gw2ScwcvweYuuWi1ZzGUtrPz918HvDtBzL5s2voFTrN4y3yUwj5cYD54pLhxk6NKCzHUjcka3zbK\
+            jbTEcsuAnkzbjhkL/0/*)"));
 
         let key_sign_cmd = Sign{
             psbt: String::from("cHNidP8BAFICAAAAAQ3TM54hf/xyGNQ3RwZ9zykQsbogN20RNgReU5yir1+IAQAAAAD9////ARAnAAAAAAAAFgAUGFotGcPnrJnJg8Mz1Htu+ejz1V8JwiMATwEENYf\
                 PAw70sa+AAAAAPIwgN+5MFiHaDTSNtRFjcJpiLQ0oON3m2EGcUfYwHGIDuI4Pvj9kYzftk7wMDzuEP899JYnl7IhHVOZAICeokLQQc8XaClQAAIABAACAAAAAgAABAHECAAAAAYDXHmXP+m\
                 71ecUGq9jRgehhR8fuWtYc89I8qZQE2zemAQAAAAD9////AlKoYwAAAAAAFgAUQsjMYy/RsZmttmBmh8tm3sjDdRudJwAAAAAAABYAFPfWsQ9R/oRyjJCODiuUa9dl46c/A8IjAAEBH50nA\
                 AAAAAAAFgAU99axD1H+hHKMkI4OK5Rr12Xjpz8BAwQBAAAAIgYC/0PHIY//lNUR6ikHqAV5i0XvloIezNiePTxpkR+h7SEYc8XaClQAAIABAACAAAAAgAAAAAASAAAAAAA="),
+            policy: None,
+            inputs: vec![],
+            trust_witness_utxo: false,
+            assume_height: None,
+            allow_all_sighashes: false,
+            no_finalize: false,
+            no_tap_internal_key: false,
+            tap_leaves: TapLeavesOptions::All,
+            no_grinding: false,
         };
 
+        let unsigned = key_sign_cmd.psbt.clone();
         let result = key_sign_cmd
-            .sign(Network::Testnet, descriptor, false)
+            .sign(Network::Testnet, descriptor.clone(), None, false)
             .unwrap();
         let is_finalized = result.get("is_finalized").unwrap().as_bool().unwrap();
         let psbt = result.get("psbt").unwrap().as_str().unwrap();
 
-        assert_eq!(is_finalized, true);
+        assert!(is_finalized);
         assert_eq!(psbt, "cHNidP8BAFICAAAAAQ3TM54hf/xyGNQ3RwZ9zykQsbogN20RNgReU5yir1+IAQAAAAD9////ARAnAAAAAAAAFgAUGFotGcPnrJnJg8Mz1Htu+ejz1V8JwiMATwEENYfPAw70s\
             a+AAAAAPIwgN+5MFiHaDTSNtRFjcJpiLQ0oON3m2EGcUfYwHGIDuI4Pvj9kYzftk7wMDzuEP899JYnl7IhHVOZAICeokLQQc8XaClQAAIABAACAAAAAgAABAHECAAAAAYDXHmXP+m71ecUGq9jR\
             gehhR8fuWtYc89I8qZQE2zemAQAAAAD9////AlKoYwAAAAAAFgAUQsjMYy/RsZmttmBmh8tm3sjDdRudJwAAAAAAABYAFPfWsQ9R/oRyjJCODiuUa9dl46c/A8IjAAEBH50nAAAAAAAAFgAU99a\
             xD1H+hHKMkI4OK5Rr12Xjpz8BAwQBAAAAIgYC/0PHIY//lNUR6ikHqAV5i0XvloIezNiePTxpkR+h7SEYc8XaClQAAIABAACAAAAAgAAAAAASAAAAAQcAAQhrAkcwRAIgK4MR5PkVnZoOriZAUX\
             rZNH0FR8aMWCOci91vCKcXPMkCIFhPzpRZASact1ejfqjtZ0Bc/crETXIKBawXYAyfsO0GASEC/0PHIY//lNUR6ikHqAV5i0XvloIezNiePTxpkR+h7SEAAA==");
+
+        // signatures of the chosen inputs only, not finalized
+        let partial_sign_cmd = |inputs: Vec<usize>| Sign {
+            psbt: unsigned.clone(),
+            policy: None,
+            inputs,
+            trust_witness_utxo: false,
+            assume_height: None,
+            allow_all_sighashes: false,
+            no_finalize: true,
+            no_tap_internal_key: false,
+            tap_leaves: TapLeavesOptions::All,
+            no_grinding: false,
+        };
+        let result = partial_sign_cmd(vec![0])
+            .sign(Network::Testnet, descriptor.clone(), None, false)
+            .unwrap();
+        assert_eq!(result["is_finalized"], false);
+        let psbt = general_purpose::STANDARD
+            .decode(result["psbt"].as_str().unwrap())
+            .unwrap();
+        let psbt = PartiallySignedTransaction::deserialize(&psbt).unwrap();
+        assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+
+        // the input spends a change address when the descriptors are swapped
+        let result = partial_sign_cmd(vec![])
+            .sign(
+                Network::Testnet,
+                Zeroizing::new(descriptor.replace("/0/*)", "/1/*)")),
+                Some(descriptor.clone()),
+                false,
+            )
+            .unwrap();
+        let psbt = general_purpose::STANDARD
+            .decode(result["psbt"].as_str().unwrap())
+            .unwrap();
+        let psbt = PartiallySignedTransaction::deserialize(&psbt).unwrap();
+        assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+
+        // a second input with a non-standard sighash and no non_witness_utxo, spending the
+        // other output of the same transaction
+        let mut psbt = PartiallySignedTransaction::deserialize(
+            &general_purpose::STANDARD.decode(&unsigned).unwrap(),
+        )
+        .unwrap();
+        let mut txin = psbt.unsigned_tx.input[0].clone();
+        txin.previous_output.vout = 0;
+        psbt.unsigned_tx.input.push(txin);
+        let mut input = psbt.inputs[0].clone();
+        input.non_witness_utxo = None;
+        input.sighash_type = Some(EcdsaSighashType::None.into());
+        psbt.inputs.push(input);
+        let two_inputs = general_purpose::STANDARD.encode(psbt.serialize());
+
+        let mut sign_cmd = partial_sign_cmd(vec![]);
+        sign_cmd.psbt = two_inputs.clone();
+        assert!(sign_cmd
+            .sign(Network::Testnet, descriptor.clone(), None, false)
+            .is_err());
+
+        // the unselected input is neither checked nor signed
+        let mut sign_cmd = partial_sign_cmd(vec![0]);
+        sign_cmd.psbt = two_inputs;
+        let result = sign_cmd
+            .sign(Network::Testnet, descriptor.clone(), None, false)
+            .unwrap();
+        let psbt = general_purpose::STANDARD
+            .decode(result["psbt"].as_str().unwrap())
+            .unwrap();
+        let psbt = PartiallySignedTransaction::deserialize(&psbt).unwrap();
+        assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+        assert!(psbt.inputs[1].partial_sigs.is_empty());
+        assert_eq!(
+            psbt.inputs[1].sighash_type,
+            Some(EcdsaSighashType::None.into())
+        );
+
+        let e = partial_sign_cmd(vec![0, 1])
+            .sign(Network::Testnet, descriptor, None, false)
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "The PSBT has no input 1, it has 1 inputs");
     }
 }
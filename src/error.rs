@@ -0,0 +1,212 @@
+use std::fmt;
+
+use bdk::bitcoin::{base58, bip32, Network};
+use bdk::descriptor::DescriptorError;
+use bdk::keys::{bip39, KeyError};
+
+use serde_json::json;
+
+/// Errors of the coldbox commands
+///
+/// Every variant maps to the process exit code our scripts branch on:
+///
+/// | code | kind       | meaning                                                      |
+/// |------|------------|--------------------------------------------------------------|
+/// | 1    | `bdk`      | any other failure (e.g. the Electrum server is unreachable)  |
+/// | 2    | `input`    | invalid or missing arguments (also used by clap)             |
+/// | 3    | `parse`    | malformed key, mnemonic, share, descriptor or PSBT           |
+/// | 4    | `checksum` | descriptor, share or key checksum mismatch                   |
+/// | 5    | `network`  | key, descriptor or address of another network                |
+/// | 6    | `signing`  | the PSBT could not be signed                                 |
+/// | 7    | `policy`   | the PSBT breaks the signing policy, it is not signed         |
+/// | 8    | `output`   | the result could not be written                              |
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid or missing arguments
+    Input(String),
+    /// Malformed key, mnemonic, share, descriptor or PSBT
+    Parse(String),
+    /// Checksum mismatch
+    Checksum(String),
+    /// Network mismatch
+    Network(String),
+    /// Signing failure
+    Signing(String),
+    /// Violations of the signing policy, `{"rule", "message"}` objects
+    Policy(Vec<serde_json::Value>),
+    /// The result could not be written
+    Output(String),
+    /// Any other bdk error
+    Bdk(bdk::Error),
+}
+
+impl Error {
+    pub fn network_mismatch(found: Network, requested: Network) -> Self {
+        Error::Network(format!(
+            "The key is for {} but the network is {}",
+            found, requested
+        ))
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Input(_) => "input",
+            Error::Parse(_) => "parse",
+            Error::Checksum(_) => "checksum",
+            Error::Network(_) => "network",
+            Error::Signing(_) => "signing",
+            Error::Policy(_) => "policy",
+            Error::Output(_) => "output",
+            Error::Bdk(_) => "bdk",
+        }
+    }
+
+    /// Process exit code
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::Bdk(_) => 1,
+            Error::Input(_) => 2,
+            Error::Parse(_) => 3,
+            Error::Checksum(_) => 4,
+            Error::Network(_) => 5,
+            Error::Signing(_) => 6,
+            Error::Policy(_) => 7,
+            Error::Output(_) => 8,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut error = json!({
+            "error": {
+                "kind": self.kind(),
+                "code": self.code(),
+                "message": self.to_string(),
+            }
+        });
+        if let Error::Policy(violations) = self {
+            error["error"]["violations"] = json!(violations);
+        }
+        error
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Input(message)
+            | Error::Parse(message)
+            | Error::Checksum(message)
+            | Error::Network(message)
+            | Error::Signing(message)
+            | Error::Output(message) => write!(f, "{}", message),
+            Error::Policy(violations) => {
+                let messages: Vec<&str> = violations
+                    .iter()
+                    .filter_map(|violation| violation["message"].as_str())
+                    .collect();
+                write!(f, "{}", messages.join("; "))
+            }
+            Error::Bdk(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<bdk::Error> for Error {
+    fn from(e: bdk::Error) -> Self {
+        let message = e.to_string();
+        match e {
+            bdk::Error::ChecksumMismatch
+            | bdk::Error::Key(KeyError::InvalidChecksum)
+            | bdk::Error::Descriptor(DescriptorError::InvalidDescriptorChecksum)
+            | bdk::Error::Bip32(bip32::Error::Base58(base58::Error::BadChecksum(..))) => {
+                Error::Checksum(message)
+            }
+            bdk::Error::InvalidNetwork { .. } | bdk::Error::Key(KeyError::InvalidNetwork) => {
+                Error::Network(message)
+            }
+            bdk::Error::Signer(_) | bdk::Error::MiniscriptPsbt(_) => Error::Signing(message),
+            bdk::Error::Key(_)
+            | bdk::Error::Descriptor(_)
+            | bdk::Error::Miniscript(_)
+            | bdk::Error::Bip32(_)
+            | bdk::Error::Secp256k1(_)
+            | bdk::Error::Hex(_)
+            | bdk::Error::Encode(_)
+            | bdk::Error::Psbt(_)
+            | bdk::Error::PsbtParse(_)
+            | bdk::Error::HardenedIndex => Error::Parse(message),
+            e => Error::Bdk(e),
+        }
+    }
+}
+
+impl From<base58::Error> for Error {
+    fn from(e: base58::Error) -> Self {
+        let message = format!("Invalid base58 string: {}", e);
+        match e {
+            base58::Error::BadChecksum(..) => Error::Checksum(message),
+            _ => Error::Parse(message),
+        }
+    }
+}
+
+impl From<bip39::Error> for Error {
+    fn from(e: bip39::Error) -> Self {
+        match e {
+            bip39::Error::InvalidChecksum => Error::Checksum(e.to_string()),
+            e => Error::Parse(e.to_string()),
+        }
+    }
+}
+
+macro_rules! impl_error {
+    ( $from:ty ) => {
+        impl From<$from> for Error {
+            fn from(e: $from) -> Self {
+                Error::from(bdk::Error::from(e))
+            }
+        }
+    };
+}
+
+impl_error!(KeyError);
+impl_error!(DescriptorError);
+impl_error!(bdk::miniscript::Error);
+impl_error!(bip32::Error);
+impl_error!(bdk::bitcoin::secp256k1::Error);
+impl_error!(bdk::bitcoin::hashes::hex::Error);
+impl_error!(bdk::bitcoin::psbt::Error);
+impl_error!(bdk::bitcoin::psbt::PsbtParseError);
+impl_error!(bdk::wallet::signer::SignerError);
+impl_error!(bdk::electrum_client::Error);
+impl_error!(serde_json::Error);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error() {
+        let e = Error::from(bdk::Error::ChecksumMismatch);
+        assert_eq!(e.code(), 4);
+        assert_eq!(e.to_json()["error"]["kind"], "checksum");
+
+        let e = Error::from(bdk::Error::Key(KeyError::InvalidNetwork));
+        assert_eq!(e.code(), 5);
+
+        let e = Error::from(bip32::Error::InvalidChildNumberFormat);
+        assert_eq!(e.code(), 3);
+
+        let e = Error::from(base58::Error::BadChecksum(1, 2));
+        assert_eq!(e.code(), 4);
+
+        let e = Error::from(bdk::Error::Generic(String::from("connection refused")));
+        assert_eq!(e.code(), 1);
+        assert_eq!(
+            e.to_json()["error"]["message"],
+            "Generic error: connection refused"
+        );
+    }
+}
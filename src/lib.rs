@@ -1,3 +1,12 @@
+pub mod account;
+pub mod bsms;
 pub mod cli;
+pub mod codex32;
 pub mod commands;
+pub mod error;
+pub mod output;
+pub mod policy;
+pub mod secure;
+pub mod slip132;
+pub mod slip39;
 pub mod util;
@@ -1,58 +1,261 @@
-use bdk::Error;
+use std::io::Write;
+
+use clap::error::ErrorKind;
 use clap::Parser;
-use log::error;
+use serde_json::json;
 
-use coldbox::cli::{Cli, CliCommand, KeyCommand, WalletCommand};
+use coldbox::cli::{
+    Cli, CliCommand, DescriptorCommand, KeyCommand, MultisigCommand, PsbtCommand, WalletCommand,
+};
+use coldbox::commands::descriptor_normalize::normalize;
+use coldbox::commands::Primary;
+use coldbox::error::Error;
+use coldbox::output::{render, Format};
+use coldbox::secure::{harden, redact, wipe_json, Zeroizing};
+use coldbox::slip132::{self, Registry};
+use coldbox::util::{keychains, prompt};
 
 fn main() {
-    env_logger::init();
+    harden();
+
+    // every record goes through redact, whatever crate it comes from
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            let message = Zeroizing::new(record.args().to_string());
+            writeln!(
+                buf,
+                "[{} {} {}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                redact(&message)
+            )
+        })
+        .init();
 
-    let cli = Cli::parse();
+    // argument errors are input errors, printed as JSON like the others
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) if e.kind() == ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
+            return fail(Error::Input(
+                "A subcommand is required, see --help".to_string(),
+            ));
+        }
+        Err(e) => {
+            let message = e.to_string();
+            let message = message.split("\n\n").next().unwrap_or_default();
+            return fail(Error::Input(
+                message.trim_start_matches("error: ").trim().to_string(),
+            ));
+        }
+    };
     let network = cli.network;
+    let format = cli.output;
+
+    if let Some(path) = &cli.slip132 {
+        if let Err(e) = Registry::load(path).and_then(slip132::init) {
+            return output(Err(e), format, "");
+        }
+    }
 
     match cli.command {
+        CliCommand::Descriptor(descriptor) => match descriptor.command {
+            DescriptorCommand::Normalize(cmd) => {
+                let result = cmd.normalize();
+                output(result, format, cmd.primary());
+            }
+        },
         CliCommand::Key(key) => match key.command {
+            KeyCommand::Bip85(cmd) => {
+                let result = cmd.bip85(network);
+                output(result, format, cmd.primary());
+            }
             KeyCommand::Change(cmd) => {
                 let result = cmd.change();
-                output(result);
+                output(result, format, cmd.primary());
+            }
+            KeyCommand::Combine(cmd) => {
+                let result = cmd.combine(network);
+                output(result, format, cmd.primary());
             }
             KeyCommand::Generate(cmd) => {
                 let result = cmd.generate(network);
-                output(result);
+                output(result, format, cmd.primary());
             }
             KeyCommand::Derive(cmd) => {
                 let result = cmd.derive(network);
-                output(result);
+                output(result, format, cmd.primary());
+            }
+            KeyCommand::Inspect(cmd) => {
+                let result = cmd.inspect();
+                output(result, format, cmd.primary());
             }
             KeyCommand::Restore(cmd) => {
                 let result = cmd.restore(network);
-                output(result);
+                output(result, format, cmd.primary());
+            }
+            KeyCommand::Split(cmd) => {
+                let result = cmd.split(network);
+                output(result, format, cmd.primary());
             }
         },
-        CliCommand::Wallet(wallet) => {
-            let descriptor = wallet.descriptor;
+        CliCommand::Multisig(multisig) => match multisig.command {
+            MultisigCommand::BsmsDescriptor(cmd) => {
+                let result = cmd.descriptor_record(network);
+                output(result, format, cmd.primary());
+            }
+            MultisigCommand::BsmsKey(cmd) => {
+                let result = cmd.key_record(network);
+                output(result, format, cmd.primary());
+            }
+            MultisigCommand::BsmsToken(cmd) => {
+                let result = cmd.token();
+                output(result, format, cmd.primary());
+            }
+            MultisigCommand::BsmsVerify(cmd) => {
+                let result = cmd.verify(network);
+                output(result, format, cmd.primary());
+            }
+            MultisigCommand::Create(cmd) => match cmd.export.as_deref() {
+                Some("coldcard") => text(format, || cmd.coldcard_file(network)),
+                _ => {
+                    let result = cmd.create(network);
+                    output(result, format, cmd.primary());
+                }
+            },
+        },
+        CliCommand::Psbt(psbt) => match psbt.command {
+            PsbtCommand::Inspect(mut cmd) => {
+                if let Some(descriptor) = cmd.descriptor.take() {
+                    let change_descriptor = cmd.change_descriptor.take().map(Zeroizing::new);
+                    let descriptors = keychains(Zeroizing::new(descriptor), change_descriptor)
+                        .and_then(|(descriptor, change_descriptor)| {
+                            Ok((
+                                wallet_descriptor(descriptor)?,
+                                change_descriptor.map(wallet_descriptor).transpose()?,
+                            ))
+                        });
+                    match descriptors {
+                        // moved back without a copy, the command wipes them on drop
+                        Ok((mut descriptor, change_descriptor)) => {
+                            cmd.descriptor = Some(std::mem::take(&mut *descriptor));
+                            cmd.change_descriptor =
+                                change_descriptor.map(|mut change| std::mem::take(&mut *change));
+                        }
+                        Err(e) => return output(Err(e), format, ""),
+                    }
+                }
+                let result = cmd.inspect(network);
+                output(result, format, cmd.primary());
+            }
+            PsbtCommand::Sign(cmd) => {
+                let result = cmd.sign(network);
+                output(result, format, cmd.primary());
+            }
+        },
+        CliCommand::Wallet(mut wallet) => {
+            let descriptor = match wallet.descriptor.take() {
+                Some(descriptor) => Zeroizing::new(descriptor),
+                None => match prompt("descriptor", false) {
+                    Ok(descriptor) => descriptor,
+                    Err(e) => return output(Err(e), format, ""),
+                },
+            };
+            let change_descriptor = wallet.change_descriptor.take().map(Zeroizing::new);
+            // a multipath descriptor sets both keychains
+            let (descriptor, change_descriptor) = match keychains(descriptor, change_descriptor) {
+                Ok(keychains) => keychains,
+                Err(e) => return output(Err(e), format, ""),
+            };
+
+            // SLIP-132 keys are changed to xpub/tpub, the only versions bdk parses
+            let descriptor = match wallet_descriptor(descriptor) {
+                Ok(descriptor) => descriptor,
+                Err(e) => return output(Err(e), format, ""),
+            };
+            let change_descriptor = match change_descriptor.map(wallet_descriptor).transpose() {
+                Ok(change_descriptor) => change_descriptor,
+                Err(e) => return output(Err(e), format, ""),
+            };
+            let server = wallet.server.clone();
             let verbose = wallet.verbose;
 
-            match wallet.command {
-                WalletCommand::Balance(_cmd) => {}
+            match &wallet.command {
+                WalletCommand::Addresses(cmd) => {
+                    let primary = cmd.primary().to_string();
+                    match cmd.format.as_str() {
+                        "csv" => text(format, || cmd.csv(network, descriptor, change_descriptor)),
+                        _ => {
+                            let result = cmd.addresses(network, descriptor, change_descriptor);
+                            output(result, format, &primary);
+                        }
+                    }
+                }
+                WalletCommand::Balance(cmd) => {
+                    let primary = cmd.primary().to_string();
+                    let result = cmd.balance(network, descriptor, change_descriptor, server);
+                    output(result, format, &primary);
+                }
                 WalletCommand::Sign(cmd) => {
-                    let result = cmd.sign(network, descriptor, verbose);
-                    output(result);
+                    let primary = cmd.primary().to_string();
+                    let result = cmd.sign(network, descriptor, change_descriptor, verbose);
+                    output(result, format, &primary);
                 }
             }
         }
     }
 }
 
-fn output(result: Result<serde_json::Value, Error>) {
+// descriptors without SLIP-132 keys are passed on untouched
+fn wallet_descriptor(descriptor: Zeroizing<String>) -> Result<Zeroizing<String>, Error> {
+    let normalized = normalize(&descriptor)?;
+    for warning in &normalized.warnings {
+        eprintln!("{}", json!({ "warning": warning }));
+    }
+
+    match normalized.keys.is_empty() {
+        true => Ok(descriptor),
+        false => Ok(normalized.descriptor),
+    }
+}
+
+fn output(result: Result<serde_json::Value, Error>, format: Format, primary: &str) {
+    let result = result.and_then(|mut value| {
+        let rendered = render(&value, format, primary);
+        wipe_json(&mut value);
+        rendered
+    });
+
     match result {
-        Ok(value) => println!("{}", serde_json::to_string_pretty(&value).unwrap()),
-        Err(e) => {
-            match e {
-                Error::ChecksumMismatch => error!("Descriptor checksum mismatch. Are you using a different descriptor for an already defined \
-                    wallet name? (if you are not specifying the wallet name it is automatically named based on the descriptor)"),
-                e => error!("{}", e.to_string()),
-            }
-        },
+        Ok(rendered) if rendered.ends_with('\n') => print!("{}", *rendered),
+        Ok(rendered) => println!("{}", *rendered),
+        Err(e) => fail(e),
+    }
+}
+
+// already formatted output (e.g. CSV), it has no primary value nor table
+fn text<F>(format: Format, text: F)
+where
+    F: FnOnce() -> Result<Zeroizing<String>, Error>,
+{
+    if format != Format::Json {
+        return fail(Error::Input(format!(
+            "This output is printed as is, it cannot be combined with --output {}",
+            format.name()
+        )));
+    }
+
+    match text() {
+        Ok(text) => print!("{}", *text),
+        Err(e) => fail(e),
     }
 }
+
+// errors go to stderr as JSON, with the exit code of their kind
+fn fail(e: Error) {
+    let mut error = e.to_json();
+    error["error"]["message"] = json!(redact(&e.to_string()));
+    eprintln!("{}", serde_json::to_string_pretty(&error).unwrap());
+    std::process::exit(e.code());
+}
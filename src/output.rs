@@ -0,0 +1,270 @@
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::secure::Zeroizing;
+
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use serde_json::Value;
+
+/// Output formats of the command results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Compact,
+    Table,
+    Plain,
+    Qr,
+}
+
+impl Format {
+    pub const ALL: [Format; 5] = [
+        Format::Json,
+        Format::Compact,
+        Format::Table,
+        Format::Plain,
+        Format::Qr,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Compact => "compact",
+            Format::Table => "table",
+            Format::Plain => "plain",
+            Format::Qr => "qr",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| format!("unknown output format {}", s))
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        Value::Array(values) => values.iter().map(scalar).collect::<Vec<_>>().join(";"),
+        value => value.to_string(),
+    }
+}
+
+// leaf values keyed by their dotted path, e.g. `accounts.bip84.xpub` or `shares.0`
+fn flatten(prefix: &str, value: &Value, rows: &mut Vec<(String, String)>) {
+    let key = |k: &str| match prefix {
+        "" => k.to_string(),
+        prefix => format!("{}.{}", prefix, k),
+    };
+    match value {
+        Value::Object(map) => map.iter().for_each(|(k, v)| flatten(&key(k), v, rows)),
+        Value::Array(values) => values
+            .iter()
+            .enumerate()
+            .for_each(|(i, v)| flatten(&key(&i.to_string()), v, rows)),
+        value => rows.push((prefix.to_string(), scalar(value))),
+    }
+}
+
+fn table(value: &Value) -> String {
+    let mut lines = vec![];
+
+    // a lone list, e.g. the targets of `key change --list`, is shown as the list itself
+    let value = match value.as_object() {
+        Some(map) if map.len() == 1 && map.values().all(Value::is_array) => {
+            map.values().next().unwrap_or(value)
+        }
+        _ => value,
+    };
+
+    match value {
+        // one row per entry, e.g. the addresses of a range
+        Value::Array(entries) if entries.iter().all(Value::is_object) && !entries.is_empty() => {
+            let columns: Vec<&String> = entries[0]
+                .as_object()
+                .into_iter()
+                .flat_map(|map| map.keys())
+                .collect();
+            let cells: Vec<Vec<String>> = entries
+                .iter()
+                .map(|entry| columns.iter().map(|c| scalar(&entry[c.as_str()])).collect())
+                .collect();
+            let widths: Vec<usize> = columns
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    cells
+                        .iter()
+                        .map(|row| row[i].len())
+                        .chain([c.len()])
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+
+            let line = |row: Vec<&str>| {
+                row.iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            };
+            lines.push(line(columns.iter().map(|c| c.as_str()).collect()));
+            for row in &cells {
+                lines.push(line(row.iter().map(String::as_str).collect()));
+            }
+        }
+        value => {
+            let mut rows = vec![];
+            flatten("", value, &mut rows);
+            let width = rows.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+            for (key, value) in rows {
+                lines.push(
+                    format!("{:width$}  {}", key, value, width = width)
+                        .trim_end()
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Values of a dotted field path, arrays along the path are walked element by element
+/// and the leaves of a final list or object are returned one by one
+pub fn primary<'a>(value: &'a Value, path: &str) -> Vec<&'a Value> {
+    let mut values = vec![value];
+    for field in path.split('.') {
+        values = values
+            .into_iter()
+            .flat_map(|value| match value {
+                Value::Array(entries) => entries.iter().collect(),
+                value => vec![value],
+            })
+            .filter_map(|value| value.get(field))
+            .collect();
+    }
+
+    let mut leaves = vec![];
+    values
+        .into_iter()
+        .for_each(|value| collect_leaves(value, &mut leaves));
+
+    leaves
+}
+
+fn collect_leaves<'a>(value: &'a Value, leaves: &mut Vec<&'a Value>) {
+    match value {
+        Value::Array(entries) => entries.iter().for_each(|v| collect_leaves(v, leaves)),
+        Value::Object(map) => map.values().for_each(|v| collect_leaves(v, leaves)),
+        value => leaves.push(value),
+    }
+}
+
+fn qr(data: &str) -> Result<String, Error> {
+    let code = QrCode::new(data.as_bytes())
+        .map_err(|e| Error::Input(format!("Cannot render the QR code: {}", e)))?;
+
+    // light modules drawn as blocks, readable on dark terminals
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
+
+/// Renders a command result, `path` is the primary field used by the plain and QR formats
+pub fn render(value: &Value, format: Format, path: &str) -> Result<Zeroizing<String>, Error> {
+    let rendered = match format {
+        Format::Json => serde_json::to_string_pretty(value)?,
+        Format::Compact => serde_json::to_string(value)?,
+        Format::Table => table(value),
+        Format::Plain | Format::Qr => {
+            let values = primary(value, path);
+            if values.is_empty() {
+                return Err(Error::Input(format!("The output has no {} field", path)));
+            }
+            let values: Vec<Zeroizing<String>> = values
+                .into_iter()
+                .map(|v| Zeroizing::new(scalar(v)))
+                .collect();
+
+            match format {
+                Format::Plain => values
+                    .iter()
+                    .map(|v| v.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => values
+                    .iter()
+                    .map(|v| qr(v))
+                    .collect::<Result<Vec<String>, Error>>()?
+                    .join("\n"),
+            }
+        }
+    };
+
+    Ok(Zeroizing::new(rendered))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_render() {
+        let value = json!({
+            "fingerprint": "73c5da0a",
+            "groups": [
+                { "member_threshold": 1, "shares": ["share a"] },
+                { "member_threshold": 2, "shares": ["share b", "share c"] },
+            ],
+        });
+
+        let rendered = render(&value, Format::Compact, "groups.shares").unwrap();
+        assert_eq!(*rendered, value.to_string());
+
+        let rendered = render(&value, Format::Plain, "groups.shares").unwrap();
+        assert_eq!(*rendered, "share a\nshare b\nshare c");
+
+        let rendered = render(&value, Format::Table, "groups.shares").unwrap();
+        assert_eq!(
+            *rendered,
+            "fingerprint                73c5da0a\n\
+             groups.0.member_threshold  1\n\
+             groups.0.shares.0          share a\n\
+             groups.1.member_threshold  2\n\
+             groups.1.shares.0          share b\n\
+             groups.1.shares.1          share c"
+        );
+
+        let entries = json!([
+            { "index": 0, "address": "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu" },
+            { "index": 1, "address": "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g" },
+        ]);
+        let rendered = render(&entries, Format::Table, "address").unwrap();
+        assert_eq!(
+            *rendered,
+            "address                                     index\n\
+             bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu  0\n\
+             bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g  1"
+        );
+
+        let rendered = render(&entries, Format::Qr, "address").unwrap();
+        assert!(rendered.contains('█'));
+
+        assert!(render(&value, Format::Plain, "xprv").is_err());
+    }
+}
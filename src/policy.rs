@@ -0,0 +1,520 @@
+use std::fs;
+use std::str::FromStr;
+
+use crate::commands::descriptor_normalize::normalize;
+use crate::commands::psbt_inspect::{add_amounts, analyze, prevout, sighash};
+use crate::error::Error;
+use crate::secure::Xprv;
+
+use bdk::bitcoin::bip32::{ExtendedPrivKey, ExtendedPubKey, Fingerprint};
+use bdk::bitcoin::psbt::{Output, PartiallySignedTransaction};
+use bdk::bitcoin::script::Instruction;
+use bdk::bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bdk::bitcoin::sighash::EcdsaSighashType;
+use bdk::bitcoin::taproot::TapNodeHash;
+use bdk::bitcoin::{Address, Network, PublicKey, Script, ScriptBuf};
+use bdk::database::MemoryDatabase;
+use bdk::descriptor::Descriptor;
+use bdk::miniscript::ForEachKey;
+use bdk::{KeychainKind, Wallet};
+
+use serde_json::json;
+
+/// Rules a PSBT must follow to be signed, read from a JSON file, e.g.
+/// `{"max_fee": 10000, "max_fee_rate": 50, "max_amount": 1000000, "allowed_destinations": ["bc1q..."],
+/// "sighash_all_only": true, "change_to_wallet": true}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    /// Maximum fee, in sat
+    pub max_fee: Option<u64>,
+    /// Maximum fee rate, in sat/vB
+    pub max_fee_rate: Option<f64>,
+    /// Maximum amount sent outside of the wallet, in sat
+    pub max_amount: Option<u64>,
+    /// Addresses and descriptors the outputs outside of the wallet may pay to
+    pub allowed_destinations: Option<Vec<String>>,
+    /// Refuses the sighashes other than SIGHASH_ALL (or DEFAULT for taproot)
+    pub sighash_all_only: bool,
+    /// Refuses the outputs with keys of the wallet which are not addresses of its descriptors
+    pub change_to_wallet: bool,
+    /// Addresses of each descriptor searched for the wallet and allowed outputs
+    pub lookahead: u32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            max_fee: None,
+            max_fee_rate: None,
+            max_amount: None,
+            allowed_destinations: None,
+            sighash_all_only: false,
+            change_to_wallet: false,
+            lookahead: 1000,
+        }
+    }
+}
+
+/// What signs the PSBT, it tells the outputs paying back to the signer
+pub enum Signer<'a> {
+    /// A descriptor wallet, its outputs are its addresses up to the lookahead
+    Wallet(&'a Wallet<MemoryDatabase>),
+    /// A master key, its outputs are the ones whose script is rebuilt from keys derived from it
+    Master(&'a ExtendedPrivKey),
+}
+
+// fingerprints of the output keys
+fn claimed(output: &Output) -> impl Iterator<Item = &Fingerprint> {
+    output
+        .bip32_derivation
+        .values()
+        .map(|(fingerprint, _)| fingerprint)
+        .chain(
+            output
+                .tap_key_origins
+                .values()
+                .map(|(_, (fingerprint, _))| fingerprint),
+        )
+}
+
+// whether the data push is in the script
+fn pushes(script: &Script, data: &[u8]) -> bool {
+    script.instructions().any(|instruction| {
+        matches!(instruction, Ok(Instruction::PushBytes(bytes)) if bytes.as_bytes() == data)
+    })
+}
+
+// whether the output pays to keys of the master key: all its keys with the master fingerprint
+// derive from it, and its script is rebuilt from one of them
+fn pays_to(
+    output: &Output,
+    script_pubkey: &ScriptBuf,
+    xprv: &ExtendedPrivKey,
+) -> Result<bool, Error> {
+    let secp = Secp256k1::new();
+    let fingerprint = xprv.fingerprint(&secp);
+    let derive = |path| -> Result<ExtendedPubKey, Error> {
+        let derived = Xprv(xprv.derive_priv(&secp, path)?);
+        Ok(ExtendedPubKey::from_priv(&secp, &derived))
+    };
+
+    let mut keys = vec![];
+    for (key, (f, path)) in &output.bip32_derivation {
+        if *f == fingerprint {
+            if derive(path)?.public_key != *key {
+                return Ok(false);
+            }
+            keys.push(PublicKey::new(*key));
+        }
+    }
+    let mut x_only_keys = vec![];
+    for (key, (_, (f, path))) in &output.tap_key_origins {
+        if *f == fingerprint {
+            if XOnlyPublicKey::from(derive(path)?.public_key) != *key {
+                return Ok(false);
+            }
+            x_only_keys.push(*key);
+        }
+    }
+
+    for key in &keys {
+        // single key scripts: pkh, wpkh and sh-wpkh
+        if *script_pubkey == ScriptBuf::new_p2pkh(&key.pubkey_hash()) {
+            return Ok(true);
+        }
+        if let Some(hash) = key.wpubkey_hash() {
+            let wpkh = ScriptBuf::new_v0_p2wpkh(&hash);
+            if *script_pubkey == wpkh || *script_pubkey == wpkh.to_p2sh() {
+                return Ok(true);
+            }
+        }
+        // multisig scripts: wsh, sh-wsh and sh, with the key in the hashed script
+        if let Some(script) = &output.witness_script {
+            let wsh = script.to_v0_p2wsh();
+            if (*script_pubkey == wsh || *script_pubkey == wsh.to_p2sh())
+                && pushes(script, &key.to_bytes())
+            {
+                return Ok(true);
+            }
+        }
+        if let Some(script) = &output.redeem_script {
+            if *script_pubkey == script.to_p2sh() && pushes(script, &key.to_bytes()) {
+                return Ok(true);
+            }
+        }
+    }
+
+    // taproot: the key is the internal key or in a leaf of the tree
+    if let Some(internal_key) = output.tap_internal_key {
+        let leaves = || output.tap_tree.iter().flat_map(|tree| tree.script_leaves());
+        let merkle_root = leaves().next().map(|leaf| {
+            leaf.merkle_branch().as_inner().iter().fold(
+                TapNodeHash::from_script(leaf.script(), leaf.version()),
+                |node, &sibling| TapNodeHash::from_node_hashes(node, sibling),
+            )
+        });
+        if *script_pubkey == ScriptBuf::new_v1_p2tr(&secp, internal_key, merkle_root) {
+            for key in &x_only_keys {
+                if *key == internal_key
+                    || leaves().any(|leaf| pushes(leaf.script(), &key.serialize()))
+                {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+fn violation(rule: &str, message: String) -> serde_json::Value {
+    json!({ "rule": rule, "message": message })
+}
+
+impl Policy {
+    pub fn from_json(config: &str) -> Result<Self, Error> {
+        let config: serde_json::Value = serde_json::from_str(config)
+            .map_err(|e| Error::Input(format!("Invalid policy: {}", e)))?;
+        let rules = config
+            .as_object()
+            .ok_or_else(|| Error::Input("The policy must be an object".to_string()))?;
+
+        let mut policy = Policy::default();
+        for (rule, value) in rules {
+            let invalid = || Error::Input(format!("Invalid policy {}: {}", rule, value));
+            match rule.as_str() {
+                "max_fee" => policy.max_fee = Some(value.as_u64().ok_or_else(invalid)?),
+                "max_fee_rate" => policy.max_fee_rate = Some(value.as_f64().ok_or_else(invalid)?),
+                "max_amount" => policy.max_amount = Some(value.as_u64().ok_or_else(invalid)?),
+                "allowed_destinations" => {
+                    let destinations = value
+                        .as_array()
+                        .ok_or_else(invalid)?
+                        .iter()
+                        .map(|entry| entry.as_str().map(String::from).ok_or_else(invalid))
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    policy.allowed_destinations = Some(destinations);
+                }
+                "sighash_all_only" => {
+                    policy.sighash_all_only = value.as_bool().ok_or_else(invalid)?
+                }
+                "change_to_wallet" => {
+                    policy.change_to_wallet = value.as_bool().ok_or_else(invalid)?
+                }
+                "lookahead" => {
+                    policy.lookahead = value
+                        .as_u64()
+                        .and_then(|n| u32::try_from(n).ok())
+                        .ok_or_else(invalid)?
+                }
+                _ => return Err(Error::Input(format!("Unknown policy rule {}", rule))),
+            }
+        }
+
+        Ok(policy)
+    }
+
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let config = fs::read_to_string(path)
+            .map_err(|e| Error::Input(format!("Cannot read {}: {}", path, e)))?;
+        Policy::from_json(&config)
+    }
+
+    // scripts of the allowed addresses, and of the first addresses of the allowed descriptors
+    fn allowed_scripts(&self, network: Network) -> Result<Vec<ScriptBuf>, Error> {
+        let mut scripts = vec![];
+        for destination in self.allowed_destinations.iter().flatten() {
+            if destination.contains('(') {
+                let normalized = normalize(destination)?;
+                let (descriptor, _) =
+                    Descriptor::parse_descriptor(&Secp256k1::new(), &normalized.descriptor)?;
+                let count = match descriptor.has_wildcard() {
+                    true => self.lookahead,
+                    false => 1,
+                };
+                for index in 0..count {
+                    let derived = descriptor.at_derivation_index(index).map_err(|e| {
+                        Error::Input(format!("Invalid allowed descriptor {}: {}", destination, e))
+                    })?;
+                    scripts.push(derived.script_pubkey());
+                }
+            } else {
+                let address = Address::from_str(destination.trim())
+                    .map_err(|e| Error::Input(format!("Invalid address {}: {}", destination, e)))?
+                    .require_network(network)
+                    .map_err(|_| {
+                        Error::Network(format!(
+                            "The address {} is not a {} address",
+                            destination, network
+                        ))
+                    })?;
+                scripts.push(address.script_pubkey());
+            }
+        }
+
+        Ok(scripts)
+    }
+
+    /// Violations of the policy by a PSBT to be signed, empty when it can be signed
+    pub fn check(
+        &self,
+        psbt: &PartiallySignedTransaction,
+        network: Network,
+        signer: Signer,
+    ) -> Result<Vec<serde_json::Value>, Error> {
+        // whether each output pays back to the signer, and the fingerprints of the signer keys
+        let (analysis, owned, fingerprints) = match signer {
+            Signer::Wallet(wallet) => {
+                wallet.ensure_addresses_cached(self.lookahead)?;
+                let change_descriptor = wallet.public_descriptor(KeychainKind::Internal)?;
+                let analysis = analyze(psbt, network, Some(wallet), change_descriptor.is_none())?;
+                let owned = analysis["outputs"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|output| !output["wallet"].is_null())
+                    .collect::<Vec<_>>();
+
+                let mut fingerprints: Vec<Fingerprint> = vec![];
+                for descriptor in [
+                    wallet.public_descriptor(KeychainKind::External)?,
+                    change_descriptor,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    descriptor.for_each_key(|key| {
+                        fingerprints.push(key.master_fingerprint());
+                        true
+                    });
+                }
+                (analysis, owned, fingerprints)
+            }
+            Signer::Master(xprv) => {
+                let analysis = analyze(psbt, network, None, true)?;
+                let owned = psbt
+                    .outputs
+                    .iter()
+                    .zip(&psbt.unsigned_tx.output)
+                    .map(|(output, txout)| pays_to(output, &txout.script_pubkey, xprv))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                (analysis, owned, vec![xprv.fingerprint(&Secp256k1::new())])
+            }
+        };
+
+        let mut violations = vec![];
+
+        if let Some(max_fee) = self.max_fee {
+            match analysis["fee"].as_i64() {
+                Some(fee) if fee > max_fee as i64 => violations.push(violation(
+                    "max_fee",
+                    format!("The fee of {} sat is above {} sat", fee, max_fee),
+                )),
+                Some(_) => {}
+                None => violations.push(violation(
+                    "max_fee",
+                    "The fee is unknown, an input has no UTXO data".to_string(),
+                )),
+            }
+        }
+        if let Some(max_fee_rate) = self.max_fee_rate {
+            match analysis["fee_rate"].as_f64() {
+                Some(fee_rate) if fee_rate > max_fee_rate => violations.push(violation(
+                    "max_fee_rate",
+                    format!(
+                        "The fee rate of {} sat/vB is above {} sat/vB",
+                        fee_rate, max_fee_rate
+                    ),
+                )),
+                Some(_) => {}
+                None => violations.push(violation(
+                    "max_fee_rate",
+                    "The fee rate is unknown, an input has no UTXO data".to_string(),
+                )),
+            }
+        }
+
+        if self.sighash_all_only {
+            for (i, input) in psbt.inputs.iter().enumerate() {
+                let (name, standard) = match prevout(psbt, i) {
+                    (Some(utxo), _) => sighash(input, &utxo.script_pubkey),
+                    // without the spent script only SIGHASH_ALL is known to be safe
+                    (None, _) => match input.sighash_type {
+                        None => ("default".to_string(), true),
+                        Some(sighash_type) => (
+                            sighash_type.to_string(),
+                            sighash_type.ecdsa_hash_ty() == Ok(EcdsaSighashType::All),
+                        ),
+                    },
+                };
+                if !standard {
+                    let mut refusal = violation(
+                        "sighash_all_only",
+                        format!("Input {} uses the sighash {}", i, name),
+                    );
+                    refusal["input"] = json!(i);
+                    violations.push(refusal);
+                }
+            }
+        }
+
+        // the outputs outside of the wallet
+        let outputs = analysis["outputs"].as_array().cloned().unwrap_or_default();
+        let external: Vec<usize> = (0..outputs.len()).filter(|&i| !owned[i]).collect();
+
+        if let Some(max_amount) = self.max_amount {
+            let amount = external.iter().try_fold(0, |total, &i| {
+                add_amounts(total, psbt.unsigned_tx.output[i].value)
+            })?;
+            if amount > max_amount {
+                violations.push(violation(
+                    "max_amount",
+                    format!(
+                        "The {} sat sent outside of the wallet are above {} sat",
+                        amount, max_amount
+                    ),
+                ));
+            }
+        }
+
+        if self.allowed_destinations.is_some() {
+            let allowed = self.allowed_scripts(network)?;
+            for &i in &external {
+                if !allowed.contains(&psbt.unsigned_tx.output[i].script_pubkey) {
+                    let address = outputs[i]["address"].as_str().unwrap_or("unknown");
+                    let mut refusal = violation(
+                        "allowed_destinations",
+                        format!("Output {} pays {}, which is not allowed", i, address),
+                    );
+                    refusal["output"] = json!(i);
+                    violations.push(refusal);
+                }
+            }
+        }
+
+        if self.change_to_wallet {
+            // an output with keys of the wallet is change, it must pay back to the wallet
+            for &i in &external {
+                if claimed(&psbt.outputs[i]).any(|fingerprint| fingerprints.contains(fingerprint)) {
+                    let mut refusal = violation(
+                        "change_to_wallet",
+                        format!(
+                            "Output {} has keys of the wallet but does not pay back to it",
+                            i
+                        ),
+                    );
+                    refusal["output"] = json!(i);
+                    violations.push(refusal);
+                }
+            }
+        }
+
+        Ok(violations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bdk::bitcoin::bip32::DerivationPath;
+    use bdk::bitcoin::opcodes::all::OP_CHECKMULTISIG;
+    use bdk::bitcoin::script::Builder;
+
+    #[test]
+    fn test_policy() {
+        // the PSBT and descriptor of the wallet sign test, 10000 sat paid with a fee of 141 sat
+        let descriptor = "wpkh([73c5da0a/84'/1'/0']tprv8fSjiqEQ8YG7Ro7gw2ScwcvweYuuWi1ZzGUtrPz918HvDtBzL5s2voFTrN4y3yUwj5cYD54pLhxk6NKCzHUjcka3zbKjbTEcsuAnkzbjhkL/0/*)";
+        let wallet = Wallet::new(
+            descriptor,
+            None,
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap();
+        let psbt = PartiallySignedTransaction::from_str("cHNidP8BAFICAAAAAQ3TM54hf/xyGNQ3RwZ9zykQsbogN20RNgReU5yir1+IAQAAAAD9////ARAnAAAAAAAAFgAUGFotGcPnrJnJg8Mz1Htu+ejz1V8JwiMATwEENYfPAw70sa+AAAAAPIwgN+5MFiHaDTSNtRFjcJpiLQ0oON3m2EGcUfYwHGIDuI4Pvj9kYzftk7wMDzuEP899JYnl7IhHVOZAICeokLQQc8XaClQAAIABAACAAAAAgAABAHECAAAAAYDXHmXP+m71ecUGq9jRgehhR8fuWtYc89I8qZQE2zemAQAAAAD9////AlKoYwAAAAAAFgAUQsjMYy/RsZmttmBmh8tm3sjDdRudJwAAAAAAABYAFPfWsQ9R/oRyjJCODiuUa9dl46c/A8IjAAEBH50nAAAAAAAAFgAU99axD1H+hHKMkI4OK5Rr12Xjpz8BAwQBAAAAIgYC/0PHIY//lNUR6ikHqAV5i0XvloIezNiePTxpkR+h7SEYc8XaClQAAIABAACAAAAAgAAAAAASAAAAAAA=").unwrap();
+
+        let policy = Policy::from_json(
+            r#"{"max_fee": 1000, "max_amount": 10000, "sighash_all_only": true, "change_to_wallet": true,
+            "allowed_destinations": ["tb1qrpdz6xwru7kfnjvrcveag7mwl850842lxz6mac"]}"#,
+        )
+        .unwrap();
+        assert!(policy
+            .check(&psbt, Network::Testnet, Signer::Wallet(&wallet))
+            .unwrap()
+            .is_empty());
+
+        let policy = Policy::from_json(
+            r#"{"max_fee": 100, "max_amount": 5000, "allowed_destinations": [], "lookahead": 10}"#,
+        )
+        .unwrap();
+        let violations = policy
+            .check(&psbt, Network::Testnet, Signer::Wallet(&wallet))
+            .unwrap();
+        let rules: Vec<&str> = violations
+            .iter()
+            .map(|v| v["rule"].as_str().unwrap())
+            .collect();
+        assert_eq!(rules, vec!["max_fee", "max_amount", "allowed_destinations"]);
+        assert_eq!(violations[2]["output"], 0);
+
+        let e = Error::Policy(violations);
+        assert_eq!(e.code(), 7);
+        assert!(e
+            .to_string()
+            .starts_with("The fee of 141 sat is above 100 sat; "));
+
+        assert!(Policy::from_json(r#"{"max_fees": 1}"#).is_err());
+
+        // the root key of the wallet, the output is paid outside until it has a key derived from it
+        let xprv = ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPe5YMU9gHen4Ez3ApihUfykaqUorj9t6FDqy3nP6eoXiAo2ssvpAjoLroQxHqr3R5nE3a5dU3DHTjTgJDd7zrbniJr6nrCzd").unwrap();
+        let policy =
+            Policy::from_json(r#"{"max_amount": 5000, "change_to_wallet": true}"#).unwrap();
+        let rules = |psbt: &PartiallySignedTransaction| -> Vec<String> {
+            policy
+                .check(psbt, Network::Testnet, Signer::Master(&xprv))
+                .unwrap()
+                .iter()
+                .map(|v| v["rule"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(rules(&psbt), vec!["max_amount"]);
+
+        let secp = Secp256k1::new();
+        let path = DerivationPath::from_str("m/84'/1'/0'/1/0").unwrap();
+        let key = ExtendedPubKey::from_priv(&secp, &xprv.derive_priv(&secp, &path).unwrap());
+        // a key of the wallet does not make the output change, its script must be built from it
+        let mut change = psbt.clone();
+        change.outputs[0]
+            .bip32_derivation
+            .insert(key.public_key, (xprv.fingerprint(&secp), path.clone()));
+        assert_eq!(rules(&change), vec!["max_amount", "change_to_wallet"]);
+
+        let key = PublicKey::new(key.public_key);
+        change.unsigned_tx.output[0].script_pubkey =
+            ScriptBuf::new_v0_p2wpkh(&key.wpubkey_hash().unwrap());
+        assert!(rules(&change).is_empty());
+
+        // a multisig change output has the key in its witness script
+        let other = PublicKey::new(*psbt.inputs[0].bip32_derivation.keys().next().unwrap());
+        let script = Builder::new()
+            .push_int(1)
+            .push_key(&other)
+            .push_key(&key)
+            .push_int(2)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script();
+        change.unsigned_tx.output[0].script_pubkey = script.to_v0_p2wsh();
+        assert_eq!(rules(&change), vec!["max_amount", "change_to_wallet"]);
+        change.outputs[0].witness_script = Some(script);
+        assert!(rules(&change).is_empty());
+
+        // the key of the input is not the one of the claimed path
+        let mut fake = psbt.clone();
+        fake.outputs[0].bip32_derivation.insert(
+            *psbt.inputs[0].bip32_derivation.keys().next().unwrap(),
+            (xprv.fingerprint(&secp), path),
+        );
+        assert_eq!(rules(&fake), vec!["max_amount", "change_to_wallet"]);
+    }
+}
@@ -0,0 +1,176 @@
+use std::ops::{Deref, DerefMut};
+
+use bdk::bitcoin::base58;
+use bdk::bitcoin::bip32::{ChainCode, ExtendedPrivKey};
+use bdk::keys::bip39::Language;
+
+pub use zeroize::{Zeroize, Zeroizing};
+
+// Best-effort protection of the key material held by the process: nothing here can
+// guarantee that no copy is left behind (the compiler is free to move values around),
+// it only narrows the window and the places where secrets can leak.
+
+const XPRV_PREFIXES: [&str; 10] = [
+    "xprv", "yprv", "zprv", "Yprv", "Zprv", "tprv", "uprv", "vprv", "Uprv", "Vprv",
+];
+
+/// Disables core dumps and locks the process memory so that secrets are neither dumped nor swapped out
+#[cfg(unix)]
+pub fn harden() {
+    unsafe {
+        let no_core = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        libc::setrlimit(libc::RLIMIT_CORE, &no_core);
+
+        // also refuses ptrace attachment from other processes of the same user
+        #[cfg(target_os = "linux")]
+        libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0);
+
+        // locking future mappings makes allocations fail once RLIMIT_MEMLOCK is reached,
+        // so only do it when the limit allows it
+        let mut memlock = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        let flags = match libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut memlock) == 0
+            && memlock.rlim_cur == libc::RLIM_INFINITY
+        {
+            true => libc::MCL_CURRENT | libc::MCL_FUTURE,
+            false => libc::MCL_CURRENT,
+        };
+        // fails without CAP_IPC_LOCK when the memory exceeds the limit, which is fine
+        libc::mlockall(flags);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn harden() {}
+
+/// Extended private key wiped on drop
+pub struct Xprv(pub ExtendedPrivKey);
+
+impl Deref for Xprv {
+    type Target = ExtendedPrivKey;
+
+    fn deref(&self) -> &ExtendedPrivKey {
+        &self.0
+    }
+}
+
+impl DerefMut for Xprv {
+    fn deref_mut(&mut self) -> &mut ExtendedPrivKey {
+        &mut self.0
+    }
+}
+
+impl Drop for Xprv {
+    fn drop(&mut self) {
+        wipe_xprv(&mut self.0);
+    }
+}
+
+pub fn wipe_xprv(xprv: &mut ExtendedPrivKey) {
+    xprv.private_key.non_secure_erase();
+    xprv.chain_code = ChainCode::from([0u8; 32]);
+}
+
+/// Wipes every string of a command output
+pub fn wipe_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) => s.zeroize(),
+        serde_json::Value::Array(values) => values.iter_mut().for_each(wipe_json),
+        serde_json::Value::Object(map) => map.values_mut().for_each(wipe_json),
+        _ => (),
+    }
+}
+
+fn is_mnemonic_word(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_alphabetic());
+    !word.is_empty()
+        && Language::all()
+            .iter()
+            .any(|language| language.find_word(word).is_some())
+}
+
+fn is_secret_token(token: &str) -> bool {
+    // extended private keys
+    (token.len() > 100 && XPRV_PREFIXES.iter().any(|prefix| token.starts_with(prefix)))
+        // WIF private keys
+        || ((51..=52).contains(&token.len())
+            && base58::decode_check(token).is_ok_and(|data| data[0] == 0x80 || data[0] == 0xef))
+        // codex32 secrets and shares
+        || (token.len() >= 48 && token.to_lowercase().starts_with("ms1"))
+}
+
+/// Masks anything that looks like key material (extended or WIF private keys, codex32 strings, mnemonics) in a message
+pub fn redact(message: &str) -> String {
+    let words: Vec<&str> = message.split(' ').collect();
+    let mut redacted = Vec::with_capacity(words.len());
+
+    let mut i = 0;
+    while i < words.len() {
+        let run = words[i..]
+            .iter()
+            .take_while(|w| is_mnemonic_word(w))
+            .count();
+        if run >= 12 {
+            redacted.push("<redacted>".to_string());
+            i += run;
+            continue;
+        }
+
+        let mut word = String::with_capacity(words[i].len());
+        let mut token = String::new();
+        for c in words[i].chars().chain(std::iter::once(' ')) {
+            if c.is_ascii_alphanumeric() {
+                token.push(c);
+                continue;
+            }
+            match is_secret_token(&token) {
+                true => word.push_str("<redacted>"),
+                false => word.push_str(&token),
+            }
+            token.zeroize();
+            if c != ' ' {
+                word.push(c);
+            }
+        }
+        redacted.push(word);
+        i += 1;
+    }
+
+    redacted.join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bdk::bitcoin::Network;
+
+    #[test]
+    fn test_redact() {
+        let message = "invalid descriptor wpkh([73c5da0a/84'/1'/0']tprv8fSjiqEQ8YG7Ro7gw2ScwcvweYuuWi1ZzGUtrPz918HvDtBzL5s2voFTrN4y3yUwj5cYD54pLhxk6NKCzHUjcka3zbKjbTEcsuAnkzbjhkL/0/*)";
+        assert_eq!(
+            redact(message),
+            "invalid descriptor wpkh([73c5da0a/84'/1'/0']<redacted>/0/*)"
+        );
+
+        let xprv = ExtendedPrivKey::new_master(Network::Testnet, &[1u8; 16]).unwrap();
+        let message = format!("key {}, mnemonic \"abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about\"", xprv.to_priv().to_wif());
+        assert_eq!(redact(&message), "key <redacted>, mnemonic <redacted>");
+
+        let message = "share ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlw";
+        assert_eq!(redact(message), "share <redacted>");
+
+        // public data is kept
+        let message = "synced tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M at height 100";
+        assert_eq!(redact(message), message);
+
+        let mut xprv = Xprv(xprv);
+        wipe_xprv(&mut xprv);
+        assert_eq!(xprv.chain_code, ChainCode::from([0u8; 32]));
+    }
+}
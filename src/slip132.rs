@@ -0,0 +1,362 @@
+use std::fs;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::error::Error;
+use crate::secure::Zeroizing;
+
+use bdk::bitcoin::base58;
+use bdk::bitcoin::hashes::hex::FromHex;
+use bdk::bitcoin::Network;
+
+use serde_json::json;
+
+// https://github.com/satoshilabs/slips/blob/master/slip-0132.md
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Private,
+    Public,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Private => "private",
+            Kind::Public => "public",
+        }
+    }
+}
+
+/// Extended key version bytes and what they stand for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// Name of the version, also the prefix of the encoded keys (e.g. "zpub")
+    pub name: String,
+    pub bytes: [u8; 4],
+    /// Network of the keys, custom versions map onto the bitcoin or testnet ones
+    pub network: Network,
+    pub kind: Kind,
+    /// Implied script type, e.g. "p2wpkh" or "p2sh-p2wsh"
+    pub script_type: String,
+    pub multisig: bool,
+}
+
+impl Version {
+    fn new(
+        name: &str,
+        bytes: u32,
+        network: Network,
+        kind: Kind,
+        script_type: &str,
+        multisig: bool,
+    ) -> Self {
+        Version {
+            name: name.to_string(),
+            bytes: bytes.to_be_bytes(),
+            network,
+            kind,
+            script_type: script_type.to_string(),
+            multisig,
+        }
+    }
+
+    pub fn hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "name": self.name,
+            "version": self.hex(),
+            "network": self.network.to_string(),
+            "kind": self.kind.name(),
+            "script_type": self.script_type,
+            "multisig": self.multisig,
+        })
+    }
+}
+
+/// Registry of the known extended key versions
+#[derive(Debug, Clone)]
+pub struct Registry {
+    versions: Vec<Version>,
+}
+
+impl Default for Registry {
+    /// The versions registered in SLIP-132
+    fn default() -> Self {
+        use Kind::{Private, Public};
+        use Network::{Bitcoin, Testnet};
+
+        let versions = [
+            ("xprv", 0x0488ade4, Bitcoin, Private, "p2pkh", false),
+            ("yprv", 0x049d7878, Bitcoin, Private, "p2sh-p2wpkh", false),
+            ("zprv", 0x04b2430c, Bitcoin, Private, "p2wpkh", false),
+            ("Yprv", 0x0295b005, Bitcoin, Private, "p2sh-p2wsh", true),
+            ("Zprv", 0x02aa7a99, Bitcoin, Private, "p2wsh", true),
+            ("tprv", 0x04358394, Testnet, Private, "p2pkh", false),
+            ("uprv", 0x044a4e28, Testnet, Private, "p2sh-p2wpkh", false),
+            ("vprv", 0x045f18bc, Testnet, Private, "p2wpkh", false),
+            ("Uprv", 0x024285b5, Testnet, Private, "p2sh-p2wsh", true),
+            ("Vprv", 0x02575048, Testnet, Private, "p2wsh", true),
+            ("xpub", 0x0488b21e, Bitcoin, Public, "p2pkh", false),
+            ("ypub", 0x049d7cb2, Bitcoin, Public, "p2sh-p2wpkh", false),
+            ("zpub", 0x04b24746, Bitcoin, Public, "p2wpkh", false),
+            ("Ypub", 0x0295b43f, Bitcoin, Public, "p2sh-p2wsh", true),
+            ("Zpub", 0x02aa7ed3, Bitcoin, Public, "p2wsh", true),
+            ("tpub", 0x043587cf, Testnet, Public, "p2pkh", false),
+            ("upub", 0x044a5262, Testnet, Public, "p2sh-p2wpkh", false),
+            ("vpub", 0x045f1cf6, Testnet, Public, "p2wpkh", false),
+            ("Upub", 0x024289ef, Testnet, Public, "p2sh-p2wsh", true),
+            ("Vpub", 0x02575483, Testnet, Public, "p2wsh", true),
+        ]
+        .into_iter()
+        .map(|(name, bytes, network, kind, script_type, multisig)| {
+            Version::new(name, bytes, network, kind, script_type, multisig)
+        })
+        .collect();
+
+        Registry { versions }
+    }
+}
+
+impl Registry {
+    pub fn versions(&self) -> &[Version] {
+        &self.versions
+    }
+
+    /// Registers a user-defined version, names and version bytes must be unique
+    pub fn add(&mut self, version: Version) -> Result<(), Error> {
+        if version.network != Network::Bitcoin && version.network != Network::Testnet {
+            return Err(Error::Input(format!(
+                "The network of version {} must be bitcoin or testnet",
+                version.name
+            )));
+        }
+        if let Some(known) = self
+            .versions
+            .iter()
+            .find(|v| v.name == version.name || v.bytes == version.bytes)
+        {
+            return Err(Error::Input(format!(
+                "Version {} ({}) conflicts with {} ({})",
+                version.name,
+                version.hex(),
+                known.name,
+                known.hex()
+            )));
+        }
+        self.versions.push(version);
+
+        Ok(())
+    }
+
+    /// Adds the versions of a JSON config, a list of
+    /// `{"name", "version", "network", "kind", "script_type", "multisig"}` objects
+    pub fn extend_json(&mut self, config: &str) -> Result<(), Error> {
+        let config: serde_json::Value = serde_json::from_str(config)
+            .map_err(|e| Error::Input(format!("Invalid version config: {}", e)))?;
+        let entries = config
+            .as_array()
+            .ok_or_else(|| Error::Input("The version config must be a list".to_string()))?;
+
+        for entry in entries {
+            let field = |name: &str| {
+                entry[name].as_str().ok_or_else(|| {
+                    Error::Input(format!(
+                        "Missing {} in version config entry {}",
+                        name, entry
+                    ))
+                })
+            };
+            let bytes = <[u8; 4]>::from_hex(field("version")?)
+                .map_err(|e| Error::Input(format!("Invalid version bytes: {}", e)))?;
+            let network = Network::from_str(field("network")?)
+                .map_err(|e| Error::Input(format!("Invalid version network: {}", e)))?;
+            let kind = match field("kind")? {
+                "private" => Kind::Private,
+                "public" => Kind::Public,
+                kind => return Err(Error::Input(format!("Invalid version kind {}", kind))),
+            };
+
+            self.add(Version {
+                name: field("name")?.to_string(),
+                bytes,
+                network,
+                kind,
+                script_type: field("script_type")?.to_string(),
+                multisig: entry["multisig"].as_bool().unwrap_or(false),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// SLIP-132 versions extended with the ones of a config file
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let config = fs::read_to_string(path)
+            .map_err(|e| Error::Input(format!("Cannot read {}: {}", path, e)))?;
+        let mut registry = Registry::default();
+        registry.extend_json(&config)?;
+
+        Ok(registry)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Version> {
+        self.versions.iter().find(|v| v.name == name)
+    }
+
+    pub fn find(&self, bytes: &[u8]) -> Option<&Version> {
+        self.versions.iter().find(|v| v.bytes == bytes)
+    }
+
+    /// Plain BIP32 version (xprv, xpub, tprv or tpub) of the same network and kind
+    pub fn standard<'a>(&'a self, version: &'a Version) -> &'a Version {
+        let name = match (version.network, version.kind) {
+            (Network::Bitcoin, Kind::Private) => "xprv",
+            (Network::Bitcoin, Kind::Public) => "xpub",
+            (_, Kind::Private) => "tprv",
+            (_, Kind::Public) => "tpub",
+        };
+        // the standard versions cannot be removed from the registry
+        self.get(name).unwrap_or(version)
+    }
+
+    /// Public version for a private one and the other way around
+    pub fn counterpart(&self, version: &Version) -> Option<&Version> {
+        self.versions.iter().find(|v| {
+            v.kind != version.kind
+                && v.network == version.network
+                && v.script_type == version.script_type
+                && v.multisig == version.multisig
+        })
+    }
+
+    /// Decodes an extended key into its version and payload
+    pub fn decode(&self, key: &str) -> Result<(&Version, Zeroizing<Vec<u8>>), Error> {
+        let data = Zeroizing::new(base58::decode_check(key)?);
+        if data.len() != 78 {
+            return Err(Error::Parse(format!(
+                "Invalid extended key length {}",
+                data.len()
+            )));
+        }
+        let version = self.find(&data[..4]).ok_or_else(|| {
+            Error::Parse(format!(
+                "Unknown extended key version {}",
+                data[..4]
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+            ))
+        })?;
+
+        Ok((version, data))
+    }
+
+    /// Versions an extended key can be converted to, i.e. the ones of the same network and kind
+    pub fn targets(&self, key: &str) -> Result<Vec<&Version>, Error> {
+        let (version, _) = self.decode(key)?;
+
+        Ok(self
+            .versions
+            .iter()
+            .filter(|v| v.network == version.network && v.kind == version.kind)
+            .collect())
+    }
+
+    /// Re-encodes an extended key with another version of the same network and kind
+    pub fn convert(&self, key: &str, target: &str) -> Result<String, Error> {
+        let (version, data) = self.decode(key)?;
+        let target = self
+            .get(target)
+            .ok_or_else(|| Error::Input(format!("Unknown key format {}", target)))?;
+        if target.kind != version.kind {
+            return Err(Error::Input(format!(
+                "Cannot convert a {} key to {}",
+                version.kind.name(),
+                target.name
+            )));
+        }
+        if target.network != version.network {
+            return Err(Error::Network(format!(
+                "Cannot convert a {} key to {}, a {} version",
+                version.network, target.name, target.network
+            )));
+        }
+
+        let data = Zeroizing::new([&target.bytes, &data[4..]].concat());
+
+        Ok(base58::encode_check(&data))
+    }
+
+    /// Re-encodes an extended key with the plain BIP32 version, parseable by the bitcoin crate
+    pub fn to_standard(&self, key: &str) -> Result<String, Error> {
+        let (version, _) = self.decode(key)?;
+        self.convert(key, &self.standard(version).name)
+    }
+}
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// Sets the process-wide registry, before any use of `registry()`
+pub fn init(registry: Registry) -> Result<(), Error> {
+    REGISTRY
+        .set(registry)
+        .map_err(|_| Error::Input("The version registry is already set".to_string()))
+}
+
+/// Process-wide registry, the SLIP-132 versions unless set by `init()`
+pub fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Registry::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::default();
+
+        let zpub = registry.convert("xpub6C1SYcQFFDMvLQxLHU6wUHGY8EP6dBtjckDGZpj4hbxw4aVuKKNAmkFbpPwzQLibWmXJmamZHKsboCdZzHqTsbeU5vi169HHHNZGdNjG2qa", "zpub");
+        assert_eq!(zpub.unwrap(), "zpub6qfy9wk5YaSt31LZxBgBtTTYUAfzWRsjSyFi8cWqTcihAn8MpdhJ1sZsrosAQA2SL3kvGXxgCeahZmrhRgfVU51fpc6rFxvFppgZQWZ7RWy");
+
+        // invalid keys are errors rather than panics
+        assert!(registry
+            .convert("xpub6C1SYcQFFDMvLQxLHU6wUHG", "zpub")
+            .is_err());
+        assert!(registry.convert("tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp", "qpub").is_err());
+        let vpub = registry.convert("xpub6C1SYcQFFDMvLQxLHU6wUHGY8EP6dBtjckDGZpj4hbxw4aVuKKNAmkFbpPwzQLibWmXJmamZHKsboCdZzHqTsbeU5vi169HHHNZGdNjG2qa", "vpub");
+        assert_eq!(vpub.unwrap_err().code(), 5);
+
+        let targets = registry.targets("tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp").unwrap();
+        let names: Vec<&str> = targets.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["tpub", "upub", "vpub", "Upub", "Vpub"]);
+
+        let zprv = registry.get("zprv").unwrap();
+        assert_eq!(registry.counterpart(zprv).unwrap().name, "zpub");
+        assert_eq!(registry.standard(zprv).name, "xprv");
+
+        // user-defined versions
+        let mut registry = Registry::default();
+        registry
+            .extend_json(r#"[{"name": "Tpub", "version": "04b2430d", "network": "bitcoin", "kind": "public", "script_type": "p2tr"}]"#)
+            .unwrap();
+        let tpub = registry.convert("xpub6C1SYcQFFDMvLQxLHU6wUHGY8EP6dBtjckDGZpj4hbxw4aVuKKNAmkFbpPwzQLibWmXJmamZHKsboCdZzHqTsbeU5vi169HHHNZGdNjG2qa", "Tpub").unwrap();
+        assert_eq!(registry.decode(&tpub).unwrap().0.script_type, "p2tr");
+        assert_eq!(
+            registry.to_standard(&tpub).unwrap(),
+            "xpub6C1SYcQFFDMvLQxLHU6wUHGY8EP6dBtjckDGZpj4hbxw4aVuKKNAmkFbpPwzQLibWmXJmamZHKsboCdZzHqTsbeU5vi169HHHNZGdNjG2qa"
+        );
+
+        // duplicate names or bytes
+        assert!(registry
+            .extend_json(r#"[{"name": "zpub", "version": "01020304", "network": "bitcoin", "kind": "public", "script_type": "p2wpkh"}]"#)
+            .is_err());
+        assert!(registry
+            .extend_json(r#"[{"name": "qpub", "version": "04b24746", "network": "bitcoin", "kind": "public", "script_type": "p2wpkh"}]"#)
+            .is_err());
+    }
+}
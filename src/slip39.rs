@@ -0,0 +1,743 @@
+// SLIP-39: Shamir's Secret-Sharing for Mnemonic Codes
+// https://github.com/satoshilabs/slips/blob/master/slip-0039.md
+
+use std::collections::BTreeMap;
+
+use crate::error::Error;
+use bdk::bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bdk::bitcoin::hashes::{sha256, Hash, HashEngine};
+
+use crate::secure::{Zeroize, Zeroizing};
+
+use rand::{thread_rng, Rng, RngCore};
+
+const RADIX_BITS: usize = 10;
+const CHECKSUM_WORDS: usize = 3;
+const HEADER_WORDS: usize = 4;
+const MIN_STRENGTH_BITS: usize = 128;
+const MIN_MNEMONIC_WORDS: usize =
+    HEADER_WORDS + CHECKSUM_WORDS + MIN_STRENGTH_BITS / RADIX_BITS + 1;
+const MAX_SHARE_COUNT: u8 = 16;
+const DIGEST_LENGTH: usize = 4;
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+
+/// A single decoded SLIP-39 share
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub identifier: u16,
+    pub extendable: bool,
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    pub value: Vec<u8>,
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+impl Share {
+    /// Parses and checksum-validates a share mnemonic
+    pub fn from_mnemonic(mnemonic: &str) -> Result<Share, Error> {
+        let words = mnemonic
+            .split_whitespace()
+            .map(|word| {
+                let word = word.to_lowercase();
+                WORDLIST
+                    .binary_search(&word.as_str())
+                    .map(|i| i as u16)
+                    .map_err(|_| Error::Parse(format!("Invalid SLIP-39 word '{}'", word)))
+            })
+            .collect::<Result<Vec<u16>, Error>>()?;
+
+        if words.len() < MIN_MNEMONIC_WORDS {
+            return Err(Error::Parse(format!(
+                "Invalid SLIP-39 mnemonic length, at least {} words are required",
+                MIN_MNEMONIC_WORDS
+            )));
+        }
+
+        let padding = (RADIX_BITS * (words.len() - HEADER_WORDS - CHECKSUM_WORDS)) % 16;
+        if padding > 8 {
+            return Err(Error::Parse("Invalid SLIP-39 mnemonic length".to_string()));
+        }
+
+        let extendable = (words[1] >> 4) & 1 == 1;
+        if polymod(
+            customization(extendable)
+                .bytes()
+                .map(u16::from)
+                .chain(words.iter().copied()),
+        ) != 1
+        {
+            return Err(Error::Checksum(format!(
+                "Invalid SLIP-39 mnemonic checksum for \"{} ...\"",
+                mnemonic
+                    .split_whitespace()
+                    .take(3)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )));
+        }
+
+        let header = words[..HEADER_WORDS]
+            .iter()
+            .fold(0u64, |acc, w| (acc << RADIX_BITS) | u64::from(*w));
+        let nibble = |shift: u64| ((header >> shift) & 0xf) as u8;
+
+        let value_bits: Vec<bool> = words[HEADER_WORDS..words.len() - CHECKSUM_WORDS]
+            .iter()
+            .flat_map(|w| (0..RADIX_BITS).rev().map(move |i| (w >> i) & 1 == 1))
+            .collect();
+        if value_bits[..padding].iter().any(|bit| *bit) {
+            return Err(Error::Parse("Invalid SLIP-39 mnemonic padding".to_string()));
+        }
+        let value = value_bits[padding..]
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, bit| (acc << 1) | *bit as u8))
+            .collect();
+
+        let share = Share {
+            identifier: (header >> 25) as u16,
+            extendable,
+            iteration_exponent: nibble(20),
+            group_index: nibble(16),
+            group_threshold: nibble(12) + 1,
+            group_count: nibble(8) + 1,
+            member_index: nibble(4),
+            member_threshold: nibble(0) + 1,
+            value,
+        };
+
+        if share.group_threshold > share.group_count {
+            return Err(Error::Parse(
+                "Invalid SLIP-39 mnemonic, group threshold greater than group count".to_string(),
+            ));
+        }
+
+        Ok(share)
+    }
+
+    /// Encodes the share as a mnemonic with its checksum
+    pub fn to_mnemonic(&self) -> String {
+        let header = (u64::from(self.identifier) << 25)
+            | (u64::from(self.extendable) << 24)
+            | (u64::from(self.iteration_exponent) << 20)
+            | (u64::from(self.group_index) << 16)
+            | (u64::from(self.group_threshold - 1) << 12)
+            | (u64::from(self.group_count - 1) << 8)
+            | (u64::from(self.member_index) << 4)
+            | u64::from(self.member_threshold - 1);
+
+        let value_words = (self.value.len() * 8).div_ceil(RADIX_BITS);
+        let padding = value_words * RADIX_BITS - self.value.len() * 8;
+        let value_bits: Vec<bool> = std::iter::repeat_n(false, padding)
+            .chain(
+                self.value
+                    .iter()
+                    .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1)),
+            )
+            .collect();
+
+        let mut words: Vec<u16> = (0..HEADER_WORDS)
+            .rev()
+            .map(|i| ((header >> (i * RADIX_BITS)) & 1023) as u16)
+            .chain(
+                value_bits
+                    .chunks(RADIX_BITS)
+                    .map(|word| word.iter().fold(0u16, |acc, bit| (acc << 1) | *bit as u16)),
+            )
+            .collect();
+
+        let checksum = polymod(
+            customization(self.extendable)
+                .bytes()
+                .map(u16::from)
+                .chain(words.iter().copied())
+                .chain([0; CHECKSUM_WORDS]),
+        ) ^ 1;
+        words.extend(
+            (0..CHECKSUM_WORDS)
+                .rev()
+                .map(|i| ((checksum >> (i * RADIX_BITS)) & 1023) as u16),
+        );
+
+        words
+            .iter()
+            .map(|w| WORDLIST[*w as usize])
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Splits a master secret into groups of share mnemonics, each group given as
+/// `(member_threshold, member_count)`
+pub fn split(
+    group_threshold: u8,
+    groups: &[(u8, u8)],
+    master_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+) -> Result<Vec<Vec<String>>, Error> {
+    if master_secret.len() * 8 < MIN_STRENGTH_BITS || !master_secret.len().is_multiple_of(2) {
+        return Err(Error::Input(format!(
+            "The master secret must be at least {} bits and a multiple of 16 bits",
+            MIN_STRENGTH_BITS
+        )));
+    }
+    check_passphrase(passphrase)?;
+    if iteration_exponent > 15 {
+        return Err(Error::Input(
+            "The iteration exponent must be between 0 and 15".to_string(),
+        ));
+    }
+    if groups.is_empty() || groups.len() > MAX_SHARE_COUNT as usize {
+        return Err(Error::Input(format!(
+            "The number of groups must be between 1 and {}",
+            MAX_SHARE_COUNT
+        )));
+    }
+    if group_threshold == 0 || group_threshold as usize > groups.len() {
+        return Err(Error::Input(format!(
+            "The group threshold must be between 1 and the number of groups ({})",
+            groups.len()
+        )));
+    }
+    for (member_threshold, member_count) in groups {
+        if *member_threshold == 0
+            || member_threshold > member_count
+            || *member_count > MAX_SHARE_COUNT
+        {
+            return Err(Error::Input(format!(
+                "Invalid group {}of{}, the threshold must be between 1 and the member count (at most {})",
+                member_threshold, member_count, MAX_SHARE_COUNT
+            )));
+        }
+        if *member_threshold == 1 && *member_count > 1 {
+            return Err(Error::Input(
+                "Creating multiple member shares with member threshold 1 is not allowed, use 1of1 instead"
+                    .to_string(),
+            ));
+        }
+    }
+
+    let identifier: u16 = thread_rng().gen_range(0..1 << 15);
+    let extendable = false;
+    let encrypted_secret = Zeroizing::new(encrypt(
+        master_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+    ));
+
+    let group_shares = split_secret(group_threshold, groups.len() as u8, &encrypted_secret)?;
+
+    group_shares
+        .into_iter()
+        .zip(groups)
+        .map(
+            |((group_index, group_secret), (member_threshold, member_count))| {
+                Ok(
+                    split_secret(*member_threshold, *member_count, &group_secret)?
+                        .into_iter()
+                        .map(|(member_index, value)| {
+                            Share {
+                                identifier,
+                                extendable,
+                                iteration_exponent,
+                                group_index,
+                                group_threshold,
+                                group_count: groups.len() as u8,
+                                member_index,
+                                member_threshold: *member_threshold,
+                                value,
+                            }
+                            .to_mnemonic()
+                        })
+                        .collect(),
+                )
+            },
+        )
+        .collect()
+}
+
+/// Recovers the master secret from enough share mnemonics
+pub fn combine(mnemonics: &[String], passphrase: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    check_passphrase(passphrase)?;
+
+    let shares = mnemonics
+        .iter()
+        .map(|m| Share::from_mnemonic(m))
+        .collect::<Result<Vec<Share>, Error>>()?;
+
+    let first = shares
+        .first()
+        .ok_or_else(|| Error::Input("No SLIP-39 shares given".to_string()))?;
+    for share in &shares {
+        if share.identifier != first.identifier
+            || share.extendable != first.extendable
+            || share.iteration_exponent != first.iteration_exponent
+            || share.group_threshold != first.group_threshold
+            || share.group_count != first.group_count
+            || share.value.len() != first.value.len()
+        {
+            return Err(Error::Parse(
+                "All SLIP-39 shares must belong to the same share set".to_string(),
+            ));
+        }
+    }
+
+    let mut groups: BTreeMap<u8, BTreeMap<u8, &Share>> = BTreeMap::new();
+    for share in &shares {
+        let members = groups.entry(share.group_index).or_default();
+        if members
+            .values()
+            .any(|member| member.member_threshold != share.member_threshold)
+        {
+            return Err(Error::Parse(format!(
+                "Mismatching member thresholds in group {}",
+                share.group_index + 1
+            )));
+        }
+        members.insert(share.member_index, share);
+    }
+
+    let group_secrets = groups
+        .iter()
+        .filter(|(_, members)| {
+            members.len() >= members.values().next().unwrap().member_threshold as usize
+        })
+        .take(first.group_threshold as usize)
+        .map(|(group_index, members)| {
+            let threshold = members.values().next().unwrap().member_threshold;
+            let member_shares: Vec<(u8, Vec<u8>)> = members
+                .values()
+                .take(threshold as usize)
+                .map(|share| (share.member_index, share.value.clone()))
+                .collect();
+            Ok((*group_index, recover_secret(threshold, &member_shares)?))
+        })
+        .collect::<Result<Vec<(u8, Vec<u8>)>, Error>>()?;
+
+    if group_secrets.len() < first.group_threshold as usize {
+        return Err(Error::Input(format!(
+            "Insufficient SLIP-39 shares, {} complete groups are required and {} were given",
+            first.group_threshold,
+            group_secrets.len()
+        )));
+    }
+
+    let encrypted_secret = Zeroizing::new(recover_secret(first.group_threshold, &group_secrets)?);
+
+    Ok(Zeroizing::new(decrypt(
+        &encrypted_secret,
+        passphrase,
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+    )))
+}
+
+fn check_passphrase(passphrase: &[u8]) -> Result<(), Error> {
+    if passphrase.iter().all(|c| (32..=126).contains(c)) {
+        Ok(())
+    } else {
+        Err(Error::Input(
+            "The passphrase must contain only printable ASCII characters".to_string(),
+        ))
+    }
+}
+
+fn customization(extendable: bool) -> &'static str {
+    if extendable {
+        "shamir_extendable"
+    } else {
+        "shamir"
+    }
+}
+
+// RS1024 checksum over GF(1024)
+fn polymod(values: impl Iterator<Item = u16>) -> u32 {
+    const GEN: [u32; 10] = [
+        0xE0E040, 0x1C1C080, 0x3838100, 0x7070200, 0xE0E0009, 0x1C0C2412, 0x38086C24, 0x3090FC48,
+        0x21B1F890, 0x3F3F120,
+    ];
+
+    values.fold(1u32, |chk, v| {
+        let b = chk >> 20;
+        let chk = ((chk & 0xFFFFF) << 10) ^ u32::from(v);
+        (0..10).fold(
+            chk,
+            |chk, i| if (b >> i) & 1 == 1 { chk ^ GEN[i] } else { chk },
+        )
+    })
+}
+
+// Feistel network with PBKDF2-HMAC-SHA256 as round function
+fn round_function(
+    i: u8,
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    salt: &[u8],
+    r: &[u8],
+) -> Vec<u8> {
+    let password = [&[i], passphrase].concat();
+    let salt = [salt, r].concat();
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / u32::from(ROUND_COUNT);
+
+    pbkdf2_sha256(&password, &salt, iterations, r.len())
+}
+
+fn salt(identifier: u16, extendable: bool) -> Vec<u8> {
+    if extendable {
+        vec![]
+    } else {
+        [customization(false).as_bytes(), &identifier.to_be_bytes()].concat()
+    }
+}
+
+fn feistel(
+    left: &[u8],
+    right: &[u8],
+    rounds: impl Iterator<Item = u8>,
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    salt: &[u8],
+) -> Vec<u8> {
+    let (mut l, mut r) = (left.to_vec(), right.to_vec());
+    for i in rounds {
+        let f = round_function(i, passphrase, iteration_exponent, salt, &r);
+        let xored = l.iter().zip(f).map(|(a, b)| a ^ b).collect();
+        l = std::mem::replace(&mut r, xored);
+    }
+    [r, l].concat()
+}
+
+fn encrypt(
+    master_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Vec<u8> {
+    let (l, r) = master_secret.split_at(master_secret.len() / 2);
+    feistel(
+        l,
+        r,
+        0..ROUND_COUNT,
+        passphrase,
+        iteration_exponent,
+        &salt(identifier, extendable),
+    )
+}
+
+fn decrypt(
+    encrypted_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Vec<u8> {
+    let (l, r) = encrypted_secret.split_at(encrypted_secret.len() / 2);
+    feistel(
+        l,
+        r,
+        (0..ROUND_COUNT).rev(),
+        passphrase,
+        iteration_exponent,
+        &salt(identifier, extendable),
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    engine.input(data);
+    Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, len: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(len);
+    for block in 1u32.. {
+        if output.len() >= len {
+            break;
+        }
+        let mut u = hmac_sha256(password, &[salt, &block.to_be_bytes()].concat());
+        let mut t = u;
+        for _ in 1..iterations {
+            u = hmac_sha256(password, &u);
+            t.iter_mut().zip(u.iter()).for_each(|(t, u)| *t ^= u);
+        }
+        output.extend_from_slice(&t);
+    }
+    output.truncate(len);
+    output
+}
+
+// GF(256) exp/log tables with the Rijndael polynomial and generator x + 1
+fn tables() -> ([u8; 255], [u8; 256]) {
+    let mut exp = [0u8; 255];
+    let mut log = [0u8; 256];
+    let mut poly: u16 = 1;
+    for (i, e) in exp.iter_mut().enumerate() {
+        *e = poly as u8;
+        log[poly as usize] = i as u8;
+        poly = (poly << 1) ^ poly;
+        if poly & 0x100 != 0 {
+            poly ^= 0x11B;
+        }
+    }
+    (exp, log)
+}
+
+fn interpolate(shares: &[(u8, Vec<u8>)], x: u8) -> Vec<u8> {
+    if let Some((_, value)) = shares.iter().find(|(share_x, _)| *share_x == x) {
+        return value.clone();
+    }
+
+    let (exp, log) = tables();
+    let log_prod: usize = shares
+        .iter()
+        .map(|(share_x, _)| log[(share_x ^ x) as usize] as usize)
+        .sum();
+
+    let mut result = vec![0u8; shares[0].1.len()];
+    for (share_x, value) in shares {
+        let others: usize = shares
+            .iter()
+            .filter(|(other_x, _)| other_x != share_x)
+            .map(|(other_x, _)| log[(share_x ^ other_x) as usize] as usize)
+            .sum();
+        let log_basis =
+            (log_prod + 255 * shares.len() - log[(share_x ^ x) as usize] as usize - others) % 255;
+        for (r, v) in result.iter_mut().zip(value) {
+            if *v != 0 {
+                *r ^= exp[(log[*v as usize] as usize + log_basis) % 255];
+            }
+        }
+    }
+    result
+}
+
+fn split_secret(
+    threshold: u8,
+    share_count: u8,
+    secret: &[u8],
+) -> Result<Vec<(u8, Vec<u8>)>, Error> {
+    if threshold == 1 {
+        return Ok((0..share_count).map(|i| (i, secret.to_vec())).collect());
+    }
+
+    let mut rng = thread_rng();
+    let mut shares: Vec<(u8, Vec<u8>)> = (0..threshold - 2)
+        .map(|i| {
+            let mut value = vec![0u8; secret.len()];
+            rng.fill_bytes(&mut value);
+            (i, value)
+        })
+        .collect();
+
+    let mut random_part = vec![0u8; secret.len() - DIGEST_LENGTH];
+    rng.fill_bytes(&mut random_part);
+    let digest = hmac_sha256(&random_part, secret);
+
+    let mut base_shares = shares.clone();
+    base_shares.push((
+        DIGEST_INDEX,
+        [&digest[..DIGEST_LENGTH], &random_part].concat(),
+    ));
+    base_shares.push((SECRET_INDEX, secret.to_vec()));
+
+    for i in threshold - 2..share_count {
+        shares.push((i, interpolate(&base_shares, i)));
+    }
+
+    Ok(shares)
+}
+
+fn recover_secret(threshold: u8, shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>, Error> {
+    if threshold == 1 {
+        return Ok(shares[0].1.clone());
+    }
+
+    let secret = interpolate(shares, SECRET_INDEX);
+    let digest_share = interpolate(shares, DIGEST_INDEX);
+    let (digest, random_part) = digest_share.split_at(DIGEST_LENGTH);
+
+    if digest != &hmac_sha256(random_part, &secret)[..DIGEST_LENGTH] {
+        return Err(Error::Checksum(
+            "Invalid digest of the shared secret".to_string(),
+        ));
+    }
+
+    Ok(secret)
+}
+
+const WORDLIST: [&str; 1024] = [
+    "academic", "acid", "acne", "acquire", "acrobat", "activity", "actress", "adapt", "adequate",
+    "adjust", "admit", "adorn", "adult", "advance", "advocate", "afraid", "again", "agency",
+    "agree", "aide", "aircraft", "airline", "airport", "ajar", "alarm", "album", "alcohol",
+    "alien", "alive", "alpha", "already", "alto", "aluminum", "always", "amazing", "ambition",
+    "amount", "amuse", "analysis", "anatomy", "ancestor", "ancient", "angel", "angry", "animal",
+    "answer", "antenna", "anxiety", "apart", "aquatic", "arcade", "arena", "argue", "armed",
+    "artist", "artwork", "aspect", "auction", "august", "aunt", "average", "aviation", "avoid",
+    "award", "away", "axis", "axle", "beam", "beard", "beaver", "become", "bedroom", "behavior",
+    "being", "believe", "belong", "benefit", "best", "beyond", "bike", "biology", "birthday",
+    "bishop", "black", "blanket", "blessing", "blimp", "blind", "blue", "body", "bolt", "boring",
+    "born", "both", "boundary", "bracelet", "branch", "brave", "breathe", "briefing", "broken",
+    "brother", "browser", "bucket", "budget", "building", "bulb", "bulge", "bumpy", "bundle",
+    "burden", "burning", "busy", "buyer", "cage", "calcium", "camera", "campus", "canyon",
+    "capacity", "capital", "capture", "carbon", "cards", "careful", "cargo", "carpet", "carve",
+    "category", "cause", "ceiling", "center", "ceramic", "champion", "change", "charity", "check",
+    "chemical", "chest", "chew", "chubby", "cinema", "civil", "class", "clay", "cleanup", "client",
+    "climate", "clinic", "clock", "clogs", "closet", "clothes", "club", "cluster", "coal",
+    "coastal", "coding", "column", "company", "corner", "costume", "counter", "course", "cover",
+    "cowboy", "cradle", "craft", "crazy", "credit", "cricket", "criminal", "crisis", "critical",
+    "crowd", "crucial", "crunch", "crush", "crystal", "cubic", "cultural", "curious", "curly",
+    "custody", "cylinder", "daisy", "damage", "dance", "darkness", "database", "daughter",
+    "deadline", "deal", "debris", "debut", "decent", "decision", "declare", "decorate", "decrease",
+    "deliver", "demand", "density", "deny", "depart", "depend", "depict", "deploy", "describe",
+    "desert", "desire", "desktop", "destroy", "detailed", "detect", "device", "devote", "diagnose",
+    "dictate", "diet", "dilemma", "diminish", "dining", "diploma", "disaster", "discuss",
+    "disease", "dish", "dismiss", "display", "distance", "dive", "divorce", "document", "domain",
+    "domestic", "dominant", "dough", "downtown", "dragon", "dramatic", "dream", "dress", "drift",
+    "drink", "drove", "drug", "dryer", "duckling", "duke", "duration", "dwarf", "dynamic", "early",
+    "earth", "easel", "easy", "echo", "eclipse", "ecology", "edge", "editor", "educate", "either",
+    "elbow", "elder", "election", "elegant", "element", "elephant", "elevator", "elite", "else",
+    "email", "emerald", "emission", "emperor", "emphasis", "employer", "empty", "ending",
+    "endless", "endorse", "enemy", "energy", "enforce", "engage", "enjoy", "enlarge", "entrance",
+    "envelope", "envy", "epidemic", "episode", "equation", "equip", "eraser", "erode", "escape",
+    "estate", "estimate", "evaluate", "evening", "evidence", "evil", "evoke", "exact", "example",
+    "exceed", "exchange", "exclude", "excuse", "execute", "exercise", "exhaust", "exotic",
+    "expand", "expect", "explain", "express", "extend", "extra", "eyebrow", "facility", "fact",
+    "failure", "faint", "fake", "false", "family", "famous", "fancy", "fangs", "fantasy", "fatal",
+    "fatigue", "favorite", "fawn", "fiber", "fiction", "filter", "finance", "findings", "finger",
+    "firefly", "firm", "fiscal", "fishing", "fitness", "flame", "flash", "flavor", "flea",
+    "flexible", "flip", "float", "floral", "fluff", "focus", "forbid", "force", "forecast",
+    "forget", "formal", "fortune", "forward", "founder", "fraction", "fragment", "frequent",
+    "freshman", "friar", "fridge", "friendly", "frost", "froth", "frozen", "fumes", "funding",
+    "furl", "fused", "galaxy", "game", "garbage", "garden", "garlic", "gasoline", "gather",
+    "general", "genius", "genre", "genuine", "geology", "gesture", "glad", "glance", "glasses",
+    "glen", "glimpse", "goat", "golden", "graduate", "grant", "grasp", "gravity", "gray",
+    "greatest", "grief", "grill", "grin", "grocery", "gross", "group", "grownup", "grumpy",
+    "guard", "guest", "guilt", "guitar", "gums", "hairy", "hamster", "hand", "hanger", "harvest",
+    "have", "havoc", "hawk", "hazard", "headset", "health", "hearing", "heat", "helpful", "herald",
+    "herd", "hesitate", "hobo", "holiday", "holy", "home", "hormone", "hospital", "hour", "huge",
+    "human", "humidity", "hunting", "husband", "hush", "husky", "hybrid", "idea", "identify",
+    "idle", "image", "impact", "imply", "improve", "impulse", "include", "income", "increase",
+    "index", "indicate", "industry", "infant", "inform", "inherit", "injury", "inmate", "insect",
+    "inside", "install", "intend", "intimate", "invasion", "involve", "iris", "island", "isolate",
+    "item", "ivory", "jacket", "jerky", "jewelry", "join", "judicial", "juice", "jump", "junction",
+    "junior", "junk", "jury", "justice", "kernel", "keyboard", "kidney", "kind", "kitchen",
+    "knife", "knit", "laden", "ladle", "ladybug", "lair", "lamp", "language", "large", "laser",
+    "laundry", "lawsuit", "leader", "leaf", "learn", "leaves", "lecture", "legal", "legend",
+    "legs", "lend", "length", "level", "liberty", "library", "license", "lift", "likely", "lilac",
+    "lily", "lips", "liquid", "listen", "literary", "living", "lizard", "loan", "lobe", "location",
+    "losing", "loud", "loyalty", "luck", "lunar", "lunch", "lungs", "luxury", "lying", "lyrics",
+    "machine", "magazine", "maiden", "mailman", "main", "makeup", "making", "mama", "manager",
+    "mandate", "mansion", "manual", "marathon", "march", "market", "marvel", "mason", "material",
+    "math", "maximum", "mayor", "meaning", "medal", "medical", "member", "memory", "mental",
+    "merchant", "merit", "method", "metric", "midst", "mild", "military", "mineral", "minister",
+    "miracle", "mixed", "mixture", "mobile", "modern", "modify", "moisture", "moment", "morning",
+    "mortgage", "mother", "mountain", "mouse", "move", "much", "mule", "multiple", "muscle",
+    "museum", "music", "mustang", "nail", "national", "necklace", "negative", "nervous", "network",
+    "news", "nuclear", "numb", "numerous", "nylon", "oasis", "obesity", "object", "observe",
+    "obtain", "ocean", "often", "olympic", "omit", "oral", "orange", "orbit", "order", "ordinary",
+    "organize", "ounce", "oven", "overall", "owner", "paces", "pacific", "package", "paid",
+    "painting", "pajamas", "pancake", "pants", "papa", "paper", "parcel", "parking", "party",
+    "patent", "patrol", "payment", "payroll", "peaceful", "peanut", "peasant", "pecan", "penalty",
+    "pencil", "percent", "perfect", "permit", "petition", "phantom", "pharmacy", "photo", "phrase",
+    "physics", "pickup", "picture", "piece", "pile", "pink", "pipeline", "pistol", "pitch",
+    "plains", "plan", "plastic", "platform", "playoff", "pleasure", "plot", "plunge", "practice",
+    "prayer", "preach", "predator", "pregnant", "premium", "prepare", "presence", "prevent",
+    "priest", "primary", "priority", "prisoner", "privacy", "prize", "problem", "process",
+    "profile", "program", "promise", "prospect", "provide", "prune", "public", "pulse", "pumps",
+    "punish", "puny", "pupal", "purchase", "purple", "python", "quantity", "quarter", "quick",
+    "quiet", "race", "racism", "radar", "railroad", "rainbow", "raisin", "random", "ranked",
+    "rapids", "raspy", "reaction", "realize", "rebound", "rebuild", "recall", "receiver",
+    "recover", "regret", "regular", "reject", "relate", "remember", "remind", "remove", "render",
+    "repair", "repeat", "replace", "require", "rescue", "research", "resident", "response",
+    "result", "retailer", "retreat", "reunion", "revenue", "review", "reward", "rhyme", "rhythm",
+    "rich", "rival", "river", "robin", "rocky", "romantic", "romp", "roster", "round", "royal",
+    "ruin", "ruler", "rumor", "sack", "safari", "salary", "salon", "salt", "satisfy", "satoshi",
+    "saver", "says", "scandal", "scared", "scatter", "scene", "scholar", "science", "scout",
+    "scramble", "screw", "script", "scroll", "seafood", "season", "secret", "security", "segment",
+    "senior", "shadow", "shaft", "shame", "shaped", "sharp", "shelter", "sheriff", "short",
+    "should", "shrimp", "sidewalk", "silent", "silver", "similar", "simple", "single", "sister",
+    "skin", "skunk", "slap", "slavery", "sled", "slice", "slim", "slow", "slush", "smart", "smear",
+    "smell", "smirk", "smith", "smoking", "smug", "snake", "snapshot", "sniff", "society",
+    "software", "soldier", "solution", "soul", "source", "space", "spark", "speak", "species",
+    "spelling", "spend", "spew", "spider", "spill", "spine", "spirit", "spit", "spray", "sprinkle",
+    "square", "squeeze", "stadium", "staff", "standard", "starting", "station", "stay", "steady",
+    "step", "stick", "stilt", "story", "strategy", "strike", "style", "subject", "submit", "sugar",
+    "suitable", "sunlight", "superior", "surface", "surprise", "survive", "sweater", "swimming",
+    "swing", "switch", "symbolic", "sympathy", "syndrome", "system", "tackle", "tactics",
+    "tadpole", "talent", "task", "taste", "taught", "taxi", "teacher", "teammate", "teaspoon",
+    "temple", "tenant", "tendency", "tension", "terminal", "testify", "texture", "thank", "that",
+    "theater", "theory", "therapy", "thorn", "threaten", "thumb", "thunder", "ticket", "tidy",
+    "timber", "timely", "ting", "tofu", "together", "tolerate", "total", "toxic", "tracks",
+    "traffic", "training", "transfer", "trash", "traveler", "treat", "trend", "trial", "tricycle",
+    "trip", "triumph", "trouble", "true", "trust", "twice", "twin", "type", "typical", "ugly",
+    "ultimate", "umbrella", "uncover", "undergo", "unfair", "unfold", "unhappy", "union",
+    "universe", "unkind", "unknown", "unusual", "unwrap", "upgrade", "upstairs", "username",
+    "usher", "usual", "valid", "valuable", "vampire", "vanish", "various", "vegan", "velvet",
+    "venture", "verdict", "verify", "very", "veteran", "vexed", "victim", "video", "view",
+    "vintage", "violence", "viral", "visitor", "visual", "vitamins", "vocal", "voice", "volume",
+    "voter", "voting", "walnut", "warmth", "warn", "watch", "wavy", "wealthy", "weapon", "webcam",
+    "welcome", "welfare", "western", "width", "wildlife", "window", "wine", "wireless", "wisdom",
+    "withdraw", "wits", "wolf", "woman", "work", "worthy", "wrap", "wrist", "writing", "wrote",
+    "year", "yelp", "yield", "yoga", "zero",
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // https://github.com/trezor/python-shamir-mnemonic/blob/master/vectors.json
+
+    #[test]
+    fn test_slip39() {
+        let mnemonics = vec![String::from("duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard")];
+        let secret = combine(&mnemonics, b"TREZOR").unwrap();
+
+        assert_eq!(
+            *secret,
+            [
+                0xbb, 0x54, 0xaa, 0xc4, 0xb8, 0x9d, 0xc8, 0x68, 0xba, 0x37, 0xd9, 0xcc, 0x21, 0xb2,
+                0xce, 0xce
+            ]
+        );
+
+        // invalid checksum
+        let mnemonics = vec![String::from("duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney")];
+        assert!(combine(&mnemonics, b"TREZOR").is_err());
+
+        // 2 of (2of3, 3of5) round trip
+        let groups = split(2, &[(2, 3), (3, 5)], &secret, b"TREZOR", 0).unwrap();
+        assert_eq!(groups[0].len(), 3);
+        assert_eq!(groups[1].len(), 5);
+
+        let shares = vec![
+            groups[0][2].clone(),
+            groups[1][0].clone(),
+            groups[0][0].clone(),
+            groups[1][4].clone(),
+            groups[1][3].clone(),
+        ];
+        assert_eq!(combine(&shares, b"TREZOR").unwrap(), secret);
+
+        // a group short of members is not enough
+        assert!(combine(&shares[..4], b"TREZOR").is_err());
+
+        // wrong passphrase gives a different secret
+        assert_ne!(combine(&shares, b"").unwrap(), secret);
+    }
+}
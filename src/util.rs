@@ -1,87 +1,254 @@
-use bdk::bitcoin::base58;
-
-pub fn prefixes(prefix: &str) -> Option<&str> {
-    match prefix {
-        // private
-        "xprv" => Some("0488ade4"),
-        "yprv" => Some("049d7878"),
-        "zprv" => Some("04b2430c"),
-        "Yprv" => Some("0295b005"),
-        "Zprv" => Some("02aa7a99"),
-        "tprv" => Some("04358394"),
-        "uprv" => Some("044a4e28"),
-        "vprv" => Some("045f18bc"),
-        "Uprv" => Some("024285b5"),
-        "Vprv" => Some("02575048"),
-        // public
-        "xpub" => Some("0488b21e"),
-        "ypub" => Some("049d7cb2"),
-        "zpub" => Some("04b24746"),
-        "Ypub" => Some("0295b43f"),
-        "Zpub" => Some("02aa7ed3"),
-        "tpub" => Some("043587cf"),
-        "upub" => Some("044a5262"),
-        "vpub" => Some("045f1cf6"),
-        "Upub" => Some("024289ef"),
-        "Vpub" => Some("02575483"),
+use std::fs;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+use crate::error::Error;
+use bdk::bitcoin::bip32::{DerivationPath, ExtendedPubKey, Fingerprint, KeySource};
+use bdk::bitcoin::taproot::TapLeafHash;
+use bdk::keys::bip39::Language;
+use bdk::miniscript::descriptor::checksum::desc_checksum;
+use bdk::miniscript::descriptor::DescriptorType;
+use bdk::signer::TapLeavesOptions;
+
+use crate::secure::Zeroizing;
+use crate::slip132::{registry, Kind};
+
+/// Names of the BIP39 wordlists accepted by `--language`
+pub const LANGUAGES: [&str; 9] = [
+    "english",
+    "chinese-simplified",
+    "chinese-traditional",
+    "czech",
+    "french",
+    "italian",
+    "japanese",
+    "korean",
+    "spanish",
+];
+
+pub fn language(name: &str) -> Option<Language> {
+    match name {
+        "english" => Some(Language::English),
+        "chinese-simplified" => Some(Language::SimplifiedChinese),
+        "chinese-traditional" => Some(Language::TraditionalChinese),
+        "czech" => Some(Language::Czech),
+        "french" => Some(Language::French),
+        "italian" => Some(Language::Italian),
+        "japanese" => Some(Language::Japanese),
+        "korean" => Some(Language::Korean),
+        "spanish" => Some(Language::Spanish),
         _ => None,
     }
 }
 
-#[allow(nonstandard_style)]
-pub struct AddressPrv {
-    // Bitcoin
-    pub xprv: String,
-    pub yprv: String,
-    pub zprv: String,
-    pub Yprv: String,
-    pub Zprv: String,
-    // Testnet
-    pub tprv: String,
-    pub uprv: String,
-    pub vprv: String,
-    pub Uprv: String,
-    pub Vprv: String,
+/// Parses an extended public key in any SLIP-132 variant (e.g. zpub, vpub) as a plain xpub/tpub,
+/// along with its key origin when given as `[fingerprint/path]key`
+pub fn xpub(key: &str) -> Result<(Option<KeySource>, ExtendedPubKey), Error> {
+    let (origin, key) = match key.strip_prefix('[').and_then(|key| key.split_once(']')) {
+        Some((origin, key)) => {
+            let (fingerprint, path) = origin.split_once('/').unwrap_or((origin, ""));
+            let fingerprint = Fingerprint::from_str(fingerprint)
+                .map_err(|e| Error::Parse(format!("Invalid key origin: {}", e)))?;
+            let path = DerivationPath::from_str(&format!("m/{}", path))?;
+            (Some((fingerprint, path)), key)
+        }
+        None => (None, key),
+    };
+
+    let registry = registry();
+    let (version, _) = registry.decode(key)?;
+    if version.kind != Kind::Public {
+        return Err(Error::Parse(format!("Not an extended public key: {}", key)));
+    }
+    let xpub = ExtendedPubKey::from_str(&registry.to_standard(key)?)?;
+
+    Ok((origin, xpub))
 }
 
-#[allow(nonstandard_style)]
-pub struct AddressPub {
-    // Bitcoin
-    pub xpub: String,
-    pub ypub: String,
-    pub zpub: String,
-    pub Ypub: String,
-    pub Zpub: String,
-    // Testnet
-    pub tpub: String,
-    pub upub: String,
-    pub vpub: String,
-    pub Upub: String,
-    pub Vpub: String,
+/// Script type of a descriptor, named like the SLIP-132 ones (e.g. "p2sh-p2wpkh")
+pub fn descriptor_script(desc_type: DescriptorType) -> &'static str {
+    match desc_type {
+        DescriptorType::Bare => "bare",
+        DescriptorType::Sh | DescriptorType::ShSortedMulti => "p2sh",
+        DescriptorType::Pkh => "p2pkh",
+        DescriptorType::Wpkh => "p2wpkh",
+        DescriptorType::Wsh | DescriptorType::WshSortedMulti => "p2wsh",
+        DescriptorType::ShWpkh => "p2sh-p2wpkh",
+        DescriptorType::ShWsh | DescriptorType::ShWshSortedMulti => "p2sh-p2wsh",
+        DescriptorType::Tr => "p2tr",
+    }
 }
 
-pub enum Address<A, B> {
-    Prv(A),
-    Pub(B),
+/// Verifies the `#checksum` suffix of a descriptor, when present
+pub fn verify_checksum(descriptor: &str) -> Result<(), Error> {
+    if let Some((body, checksum)) = descriptor.split_once('#') {
+        let expected = desc_checksum(body)?;
+        if checksum != expected {
+            return Err(Error::Checksum(format!(
+                "Invalid descriptor checksum {}, expected {}",
+                checksum, expected
+            )));
+        }
+    }
+
+    Ok(())
 }
 
-pub fn get_version(key: &str, format: &str) -> Option<String> {
-    let prefix_origin: &str = prefixes(format).unwrap();
-
-    let prefix_target: Vec<u8> = prefix_origin
-        .chars()
-        .collect::<Vec<char>>()
-        .chunks(2)
-        .map(|dig| {
-            let d: String = dig.into_iter().collect();
-            u8::from_str_radix(&d, 16).unwrap()
-        })
-        .collect();
+/// Parses a SLIP-39 share group given as member threshold and count (e.g. "2of3")
+pub fn group(s: &str) -> Result<(u8, u8), String> {
+    let (threshold, count) = s
+        .split_once("of")
+        .ok_or_else(|| format!("invalid group '{}', expected THRESHOLDofCOUNT", s))?;
+    let threshold = threshold.trim().parse::<u8>().map_err(|e| e.to_string())?;
+    let count = count.trim().parse::<u8>().map_err(|e| e.to_string())?;
+
+    Ok((threshold, count))
+}
+
+/// Splits a BIP389 multipath descriptor (e.g. `.../<0;1>/*`) into the receive and change
+/// descriptors, other descriptors are kept with the given change descriptor
+pub fn keychains(
+    descriptor: Zeroizing<String>,
+    change_descriptor: Option<Zeroizing<String>>,
+) -> Result<(Zeroizing<String>, Option<Zeroizing<String>>), Error> {
+    verify_checksum(&descriptor)?;
+    let body = descriptor
+        .split_once('#')
+        .map_or(descriptor.as_str(), |(body, _)| body);
+
+    // every `<a;b;...>` of the descriptor, with its position
+    let mut tuples = vec![];
+    let mut start = 0;
+    while let Some(open) = body[start..].find('<').map(|i| start + i) {
+        let close = body[open..]
+            .find('>')
+            .map(|i| open + i)
+            .ok_or_else(|| Error::Parse("Unclosed multipath <".to_string()))?;
+        let paths: Vec<&str> = body[open + 1..close].split(';').collect();
+        tuples.push((open, close, paths));
+        start = close + 1;
+    }
+
+    if tuples.is_empty() {
+        return Ok((descriptor, change_descriptor));
+    }
+    if tuples.iter().any(|(_, _, paths)| paths.len() != 2) {
+        return Err(Error::Input(
+            "A multipath descriptor must have 2 paths, <receive;change>".to_string(),
+        ));
+    }
+    if change_descriptor.is_some() {
+        return Err(Error::Input(
+            "The multipath descriptor already sets the change descriptor".to_string(),
+        ));
+    }
+
+    let single = |path: usize| {
+        let mut single = Zeroizing::new(String::new());
+        let mut start = 0;
+        for (open, close, paths) in &tuples {
+            single.push_str(&body[start..*open]);
+            single.push_str(paths[path]);
+            start = close + 1;
+        }
+        single.push_str(&body[start..]);
+        single
+    };
+
+    Ok((single(0), Some(single(1))))
+}
+
+/// Parses the taproot leaves to sign: "all", "none", "include:HASH,..." or "exclude:HASH,..."
+pub fn tap_leaves(s: &str) -> Result<TapLeavesOptions, String> {
+    let hashes = |list: &str| {
+        list.split(',')
+            .map(|hash| TapLeafHash::from_str(hash.trim()).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, String>>()
+    };
+    match s.split_once(':') {
+        None if s == "all" => Ok(TapLeavesOptions::All),
+        None if s == "none" => Ok(TapLeavesOptions::None),
+        Some(("include", list)) => Ok(TapLeavesOptions::Include(hashes(list)?)),
+        Some(("exclude", list)) => Ok(TapLeavesOptions::Exclude(hashes(list)?)),
+        _ => Err(format!(
+            "invalid leaves '{}', expected all, none, include:HASH,... or exclude:HASH,...",
+            s
+        )),
+    }
+}
 
-    let data = base58::decode_check(key).unwrap();
-    let data = [&prefix_target, &data[4..]].concat();
+/// Resolves a secret argument: `-` reads it from stdin, `@path` from a file, anything else is the secret itself
+pub fn secret(value: &str) -> Result<String, String> {
+    let secret = match value {
+        "-" => {
+            let mut buf = Zeroizing::new(String::new());
+            io::stdin()
+                .read_to_string(&mut buf)
+                .map_err(|e| format!("cannot read stdin: {}", e))?;
+            buf
+        }
+        _ => match value.strip_prefix('@') {
+            Some(path) => Zeroizing::new(
+                fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?,
+            ),
+            None => return Ok(value.to_string()),
+        },
+    };
+    let mut secret = secret;
+    let len = secret.trim_end_matches(['\r', '\n']).len();
+    secret.truncate(len);
 
-    Some(base58::encode_check(&data))
+    if secret.is_empty() {
+        return Err(format!("no secret found in {}", value));
+    }
+
+    // moved out without a copy, the fields holding it are wiped by their Drop
+    Ok(std::mem::take(&mut *secret))
+}
+
+/// Reads a secret from the terminal without echo, asking twice when `confirm` is set
+pub fn prompt(name: &str, confirm: bool) -> Result<Zeroizing<String>, Error> {
+    let read = |prompt: String| {
+        rpassword::prompt_password(prompt)
+            .map(Zeroizing::new)
+            .map_err(|e| Error::Input(format!("Cannot read the {}: {}", name, e)))
+    };
+
+    let secret = read(format!("{}: ", name))?;
+    if confirm && read(format!("Confirm {}: ", name))? != secret {
+        return Err(Error::Input(format!("The {}s do not match", name)));
+    }
+
+    Ok(secret)
+}
+
+/// Resolves an optional passphrase argument, prompting for it when the flag is given without a value
+pub fn passphrase(value: &Option<Option<String>>, name: &str) -> Result<Zeroizing<String>, Error> {
+    match value {
+        Some(Some(passphrase)) => Ok(Zeroizing::new(passphrase.clone())),
+        Some(None) => prompt(name, true),
+        None => Ok(Zeroizing::new(String::new())),
+    }
+}
+
+/// Resolves a required secret argument, prompting for it when omitted
+pub fn required(value: &Option<String>, name: &str) -> Result<Zeroizing<String>, Error> {
+    match value {
+        Some(value) => Ok(Zeroizing::new(value.clone())),
+        None => prompt(name, false),
+    }
+}
+
+/// Splits secret arguments holding one or more shares (e.g. read from a file) into single shares
+pub fn shares(values: &[String]) -> Zeroizing<Vec<String>> {
+    let shares = values
+        .iter()
+        .flat_map(|value| value.lines())
+        .map(|share| share.trim().to_string())
+        .filter(|share| !share.is_empty())
+        .collect();
+
+    Zeroizing::new(shares)
 }
 
 #[cfg(test)]
@@ -89,8 +256,49 @@ mod test {
     use super::*;
 
     #[test]
-    fn test_key_derive() {
-        let zpub = get_version("xpub6C1SYcQFFDMvLQxLHU6wUHGY8EP6dBtjckDGZpj4hbxw4aVuKKNAmkFbpPwzQLibWmXJmamZHKsboCdZzHqTsbeU5vi169HHHNZGdNjG2qa", "zpub");
-        assert_eq!(zpub, Some("zpub6qfy9wk5YaSt31LZxBgBtTTYUAfzWRsjSyFi8cWqTcihAn8MpdhJ1sZsrosAQA2SL3kvGXxgCeahZmrhRgfVU51fpc6rFxvFppgZQWZ7RWy".to_owned()));
+    fn test_secret() {
+        let path = std::env::temp_dir().join(format!("coldbox-secret-{}", std::process::id()));
+
+        fs::write(&path, "abandon abandon about\n").unwrap();
+        let value = format!("@{}", path.display());
+        assert_eq!(secret(&value).unwrap(), "abandon abandon about");
+
+        // empty file
+        fs::write(&path, "\n").unwrap();
+        assert!(secret(&value).is_err());
+        fs::remove_file(&path).unwrap();
+
+        // missing file
+        assert!(secret(&value).is_err());
+
+        // plain value
+        assert_eq!(secret("tprv8ZgxM").unwrap(), "tprv8ZgxM");
+        assert_eq!(
+            *shares(&[String::from("ms1a\n\nms1b \n"), String::from("ms1c")]),
+            ["ms1a", "ms1b", "ms1c"]
+        );
+    }
+
+    #[test]
+    fn test_keychains() {
+        let key = "[73c5da0a/84'/1'/0']tprv8fSjiqEQ8YG7Ro7gw2ScwcvweYuuWi1ZzGUtrPz918HvDtBzL5s2voFTrN4y3yUwj5cYD54pLhxk6NKCzHUjcka3zbKjbTEcsuAnkzbjhkL";
+        let descriptor = |text: String| Zeroizing::new(text);
+        let (receive, change) =
+            keychains(descriptor(format!("wpkh({}/<0;1>/*)", key)), None).unwrap();
+        assert_eq!(*receive, format!("wpkh({}/0/*)", key));
+        assert_eq!(*change.unwrap(), format!("wpkh({}/1/*)", key));
+
+        // single path descriptors are kept
+        let single = format!("wpkh({}/0/*)", key);
+        let (receive, change) = keychains(descriptor(single.clone()), None).unwrap();
+        assert_eq!(*receive, single);
+        assert!(change.is_none());
+
+        assert!(keychains(descriptor(format!("wpkh({}/<0;1;2>/*)", key)), None).is_err());
+        assert!(keychains(
+            descriptor(format!("wpkh({}/<0;1>/*)", key)),
+            Some(descriptor(format!("wpkh({}/1/*)", key)))
+        )
+        .is_err());
     }
 }